@@ -3,7 +3,7 @@ use std::time::Instant;
 use clap::Parser;
 use jdb_pc::{
   Pc,
-  types::{ExPenalty, PcConf},
+  types::{BlockMode, ExPenalty, PcConf},
 };
 use rand::prelude::*;
 use rand_distr::Distribution;
@@ -21,6 +21,11 @@ struct Args {
   #[arg(long, default_value_t = 1)]
   ex_penalty: u8,
 
+  /// Cut blocks where the epsilon line breaks instead of every B elements
+  /// 在 epsilon 直线失效处分块，而非每 B 个元素
+  #[arg(long)]
+  eps_blocks: bool,
+
   /// Data size (default 1000 MiB = 131,072,000 u64s)
   /// 数据大小（默认 1000 MiB = 131,072,000 u64s）
   #[arg(short, long, default_value_t = 131_072_000)]
@@ -74,10 +79,22 @@ fn measure_vec(data: &[u64], n_queries: usize) -> (f64, f64, f64) {
   (size_mb, get_mops, p99)
 }
 
-fn measure_pc(data: &[u64], conf: PcConf, n_queries: usize) -> (f64, f64, f64) {
+/// Exceptions stored, their size in KiB, and the KiB the previous layout
+/// (one bitmap bit per value plus a flat 64-bit code per exception) needed
+/// 异常值个数、其占用 KiB，以及旧布局（每值一位位图加每个异常一个 64 位码）
+/// 所需的 KiB
+fn exception_kib(pc: &Pc) -> (usize, f64, f64) {
+  let count = pc.exception_count();
+  let now = (pc.exceptions.len() * 8) as f64 / 1024.0;
+  let flat = (pc.len.div_ceil(64) * 8 + count * 8) as f64 / 1024.0;
+  (count, now, flat)
+}
+
+fn measure_pc(data: &[u64], conf: PcConf, n_queries: usize) -> (f64, f64, f64, (usize, f64, f64)) {
   let n = data.len();
   let pc = Pc::new_with_conf(data, conf);
   let size_mb = pc.size_in_bytes() as f64 / 1024.0 / 1024.0;
+  let ex = exception_kib(&pc);
 
   let mut rng = StdRng::seed_from_u64(SEED);
   // Throughput
@@ -110,7 +127,7 @@ fn measure_pc(data: &[u64], conf: PcConf, n_queries: usize) -> (f64, f64, f64) {
   latencies.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
   let p99 = latencies[(latencies.len() as f64 * 0.99) as usize];
 
-  (size_mb, get_mops, p99)
+  (size_mb, get_mops, p99, ex)
 }
 
 fn main() {
@@ -153,11 +170,13 @@ fn main() {
 
   // 3. Candidate
   // 3. 候选测试
-  let conf = PcConf {
-    epsilon: args.epsilon,
-    ex_penalty: ExPenalty::new(args.ex_penalty),
-  };
-  let (pc_size, pc_mops, pc_p99) = measure_pc(&data, conf, n_queries);
+  let conf =
+    PcConf::new(args.epsilon, ExPenalty::new(args.ex_penalty)).with_mode(if args.eps_blocks {
+      BlockMode::Epsilon
+    } else {
+      BlockMode::Fixed
+    });
+  let (pc_size, pc_mops, pc_p99, (ex_count, ex_kib, flat_kib)) = measure_pc(&data, conf, n_queries);
 
   // 4. Check Constraints
   // DRAM <= 30% or Reduction >= 70%
@@ -194,16 +213,21 @@ fn main() {
 
   println!("{:.4}", score);
   eprintln!(
-    "Result: N={} Eps={} Pen={}\n  Base: Size={:.2}MB Mops={:.2} P99={:.0}ns\n  Pc:   Size={:.2}MB Mops={:.2} P99={:.0}ns\n  Ratios: Size={:.2} (Goal<=0.30) Tpt={:.2} (Goal>=0.95) Lat={:.2} (Goal<=1.10)\n  Pass: {:?} Score={:.4}",
+    "Result: N={} Eps={} Pen={} Mode={:?}\n  Base: Size={:.2}MB Mops={:.2} P99={:.0}ns\n  Pc:   Size={:.2}MB Mops={:.2} P99={:.0}ns\n  Exceptions: {} in {:.1}KiB (bitmap + flat codes: {:.1}KiB, saved {:.1}KiB)\n  Ratios: Size={:.2} (Goal<=0.30) Tpt={:.2} (Goal>=0.95) Lat={:.2} (Goal<=1.10)\n  Pass: {:?} Score={:.4}",
     n,
     args.epsilon,
     args.ex_penalty,
+    conf.mode,
     base_size,
     base_mops,
     base_p99,
     pc_size,
     pc_mops,
     pc_p99,
+    ex_count,
+    ex_kib,
+    flat_kib,
+    flat_kib - ex_kib,
     size_ratio,
     tpt_ratio,
     lat_ratio,
@@ -0,0 +1,295 @@
+//! Streaming construction and append-only growth
+//! 流式构建与仅追加增长
+//!
+//! Values are buffered only until a block can no longer change: `B` of them
+//! in fixed mode, `B * MAX_BLOCK_MUL` (the longest block) in epsilon mode.
+//! The result is identical to building from the whole slice at once.
+//! 值只缓冲到其所在块不会再变为止：固定模式 `B` 个，epsilon 模式
+//! `B * MAX_BLOCK_MUL` 个（最长块）。结果与一次性从整个切片构建完全相同。
+
+use std::marker::PhantomData;
+
+use jdb_pgm_lib::bits::BitWriter;
+
+use super::{
+  PcBase, build_dir, cone,
+  enc::{Enc, line_slope},
+  types::{BlockMode, MAX_BLOCK_MUL, MIN_CONE_DIV, PcConf, PcInt, super_shift},
+};
+
+/// Encoded blocks and where the next one starts.
+/// 已编码的块及下一块的起点。
+struct Sink<T: PcInt> {
+  enc: Enc<T>,
+  starts: Vec<u64>,
+  encoded: usize,
+}
+
+impl<T: PcInt> Sink<T> {
+  /// Encode one block from the head of `data`; returns its length.
+  /// 从 `data` 开头编码一个块，返回其长度。
+  fn block<const B: usize>(&mut self, data: &[T], conf: &PcConf) -> usize {
+    let (len, slope) = match conf.mode {
+      // Block Model: line from the first value to the last
+      // 块模型：首值到末值的直线
+      BlockMode::Fixed => {
+        let len = data.len().min(B);
+        (len, line_slope(&data[..len]))
+      }
+      // Blocks end where no line through the first value stays within
+      // ±epsilon; a run too short to pay for its meta becomes a fixed block
+      // 当没有过首值的直线能保持在 ±epsilon 内时结束该块；
+      // 短到抵不上块元数据开销的段改为固定块
+      BlockMode::Epsilon => {
+        self.starts.push(self.encoded as u64);
+        let (len, slope) = cone::fit(data, conf.epsilon as u64, B * MAX_BLOCK_MUL);
+        if len < (B / MIN_CONE_DIV).min(data.len()) {
+          let len = data.len().min(B);
+          (len, line_slope(&data[..len]))
+        } else {
+          (len, slope)
+        }
+      }
+    };
+    self.enc.push(&data[..len], slope);
+    self.encoded += len;
+    len
+  }
+}
+
+/// Builds a `PcBase` from values pushed one at a time or in chunks.
+/// 由逐个或分批推入的值构建 `PcBase`。
+pub struct PcBuilderBase<const B: usize, T: PcInt = u64> {
+  sink: Sink<T>,
+  pending: Vec<T>,
+  conf: PcConf,
+}
+
+impl<const B: usize, T: PcInt> PcBuilderBase<B, T> {
+  /// Empty builder.
+  /// 空构建器。
+  pub fn new(conf: PcConf) -> Self {
+    Self::with_capacity(conf, 0)
+  }
+
+  /// Empty builder sized for about `n` values.
+  /// 按约 `n` 个值预留容量的空构建器。
+  pub fn with_capacity(conf: PcConf, n: usize) -> Self {
+    Self {
+      sink: Sink {
+        enc: Enc::new(n, conf.ex_penalty.get(), super_shift(B), conf.large),
+        starts: Vec::new(),
+        encoded: 0,
+      },
+      pending: Vec::with_capacity(Self::ready(&conf)),
+      conf,
+    }
+  }
+
+  /// Values needed before the first buffered block is final.
+  /// 首个缓冲块定型前所需的值个数。
+  #[inline]
+  fn ready(conf: &PcConf) -> usize {
+    match conf.mode {
+      BlockMode::Fixed => B,
+      BlockMode::Epsilon => B * MAX_BLOCK_MUL,
+    }
+  }
+
+  /// Values pushed so far.
+  /// 已推入的值个数。
+  #[inline]
+  pub fn len(&self) -> usize {
+    self.sink.encoded + self.pending.len()
+  }
+
+  /// Whether nothing has been pushed.
+  /// 是否尚未推入任何值。
+  #[inline]
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  /// Append one value.
+  /// 追加一个值。
+  #[inline]
+  pub fn push(&mut self, value: T) {
+    self.pending.push(value);
+    if self.pending.len() >= Self::ready(&self.conf) {
+      self.drain();
+    }
+  }
+
+  /// Append a chunk; whole blocks are encoded straight from it.
+  /// 追加一批值；完整的块直接从中编码。
+  pub fn extend_from_slice(&mut self, mut values: &[T]) {
+    let ready = Self::ready(&self.conf);
+    while !values.is_empty() {
+      if self.pending.is_empty() {
+        while values.len() >= ready {
+          let n = self.sink.block::<B>(values, &self.conf);
+          values = &values[n..];
+        }
+        self.pending.extend_from_slice(values);
+        return;
+      }
+      let take = (ready - self.pending.len()).min(values.len());
+      self.pending.extend_from_slice(&values[..take]);
+      values = &values[take..];
+      self.drain();
+    }
+  }
+
+  /// Encode buffered blocks that can no longer change.
+  /// 编码不会再变化的缓冲块。
+  fn drain(&mut self) {
+    let ready = Self::ready(&self.conf);
+    let mut at = 0;
+    while self.pending.len() - at >= ready {
+      at += self.sink.block::<B>(&self.pending[at..], &self.conf);
+    }
+    self.pending.drain(..at);
+  }
+
+  /// Encode what is left and return the finished `PcBase`.
+  /// 编码剩余的值并返回完成的 `PcBase`。
+  pub fn finish(mut self) -> PcBase<B, T> {
+    let mut at = 0;
+    while at < self.pending.len() {
+      at += self.sink.block::<B>(&self.pending[at..], &self.conf);
+    }
+    let Sink {
+      enc,
+      mut starts,
+      encoded: len,
+    } = self.sink;
+    if len == 0 {
+      return PcBase {
+        mode: self.conf.mode,
+        epsilon: self.conf.epsilon,
+        ex_penalty: self.conf.ex_penalty,
+        ..PcBase::default()
+      };
+    }
+    if self.conf.mode == BlockMode::Epsilon {
+      starts.push(len as u64);
+    }
+
+    let (dir, dir_shift) = build_dir(&starts);
+    PcBase {
+      mode: self.conf.mode,
+      epsilon: self.conf.epsilon,
+      ex_penalty: self.conf.ex_penalty,
+      block_meta: enc.block_meta,
+      residuals: enc.bit_writer.finish(),
+      exceptions: enc.ex_writer.finish(),
+      starts,
+      dir,
+      dir_shift,
+      anchors: enc.anchors,
+      len,
+      elem: PhantomData,
+    }
+  }
+}
+
+impl<const B: usize, T: PcInt> Extend<T> for PcBuilderBase<B, T> {
+  fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+    for v in iter {
+      self.push(v);
+    }
+  }
+}
+
+impl<const B: usize, T: PcInt> PcBase<B, T> {
+  /// Turn back into a builder that continues after the last value, with the
+  /// configuration `self` was built with. A block that could still grow (the
+  /// partial fixed block, or the last epsilon block) is decoded into the
+  /// buffer and re-encoded later.
+  /// 转回构建器，以 `self` 的构建配置从最后一个值之后继续。仍可能增长的块
+  /// （不满的固定块，或最后一个 epsilon 块）会解码进缓冲区，稍后重新编码。
+  pub fn into_builder(self) -> PcBuilderBase<B, T> {
+    let conf = self.conf();
+    self.into_builder_with(conf)
+  }
+
+  /// As `into_builder`, continuing with another epsilon, penalty or large
+  /// mode; `conf.mode` is taken from `self`.
+  /// 同 `into_builder`，但以其他 epsilon、惩罚系数或大模式继续；
+  /// `conf.mode` 取自 `self`。
+  pub fn into_builder_with(self, conf: PcConf) -> PcBuilderBase<B, T> {
+    let conf = PcConf {
+      mode: self.mode,
+      ..conf
+    };
+    let self_large = self.is_large();
+    let blocks = self.block_count();
+    // Cut point: blocks kept, first index, residual and exception bit offsets
+    // 截断点：保留的块数、首索引、残差与异常位偏移
+    let (kept, start, bit_off, ex_off, pending) = match blocks.checked_sub(1) {
+      None => (0, 0, 0, 0, Vec::new()),
+      Some(b_idx) => {
+        let view = self.view();
+        let (start, end) = view.block_span(b_idx);
+        let meta = &self.block_meta[b_idx];
+        let (bit_off, ex_off) = view.offsets(b_idx, meta);
+        let reopen = self.mode == BlockMode::Epsilon || end - start < B;
+        if reopen {
+          let values = view.iter_range(start..end).collect();
+          (b_idx, start, bit_off, ex_off, values)
+        } else {
+          let bit_end = bit_off + (end - start) * meta.bit_width as usize;
+          let ex_end = ex_off + meta.ex_bits(end - start);
+          (blocks, end, bit_end, ex_end, Vec::new())
+        }
+      }
+    };
+
+    let PcBase {
+      mut block_meta,
+      residuals,
+      exceptions,
+      mut starts,
+      mut anchors,
+      ..
+    } = self;
+    block_meta.truncate(kept);
+    starts.truncate(kept);
+    // The dropped block may have opened a superblock
+    // 丢弃的块可能开启过一个超块
+    anchors.truncate(kept.div_ceil(1 << super_shift(B)) * 2);
+
+    let mut enc = Enc::new(0, conf.ex_penalty.get(), super_shift(B), self_large);
+    enc.block_meta = block_meta;
+    enc.bit_writer = BitWriter::resume(residuals, bit_off);
+    enc.ex_writer = BitWriter::resume(exceptions, ex_off);
+    enc.anchors = anchors;
+    if conf.large && !self_large {
+      enc.promote();
+    }
+
+    let mut builder = PcBuilderBase {
+      sink: Sink {
+        enc,
+        starts,
+        encoded: start,
+      },
+      pending: Vec::with_capacity(PcBuilderBase::<B, T>::ready(&conf)),
+      conf,
+    };
+    builder.extend_from_slice(&pending);
+    builder
+  }
+
+  /// Append `values` in place with the build configuration, reopening the
+  /// last block if it could still grow.
+  /// 以构建配置原地追加 `values`，若最后一块仍可能增长则重新打开它。
+  pub fn append(&mut self, values: &[T]) {
+    if values.is_empty() {
+      return;
+    }
+    let mut builder = std::mem::take(self).into_builder();
+    builder.extend_from_slice(values);
+    *self = builder.finish();
+  }
+}
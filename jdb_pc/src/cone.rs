@@ -0,0 +1,62 @@
+//! Integer shrinking cone for epsilon-driven blocks
+//! 整数收缩锥，用于 epsilon 驱动的分块
+//!
+//! The line is anchored at the first value; every later point `(x, y)` narrows
+//! the slope to `[(y - y0 - eps) / x, (y - y0 + eps) / x]`. Bounds are kept as
+//! exact fractions, so no float rounding can admit a point outside ±eps.
+//! 直线锚定在首值；之后每个点 `(x, y)` 都把斜率收窄到
+//! `[(y - y0 - eps) / x, (y - y0 + eps) / x]`。边界以精确分数保存，
+//! 不会因浮点舍入放入超出 ±eps 的点。
+
+use super::types::PcInt;
+
+/// Longest prefix of `values` (at most `max_len`) that one line through
+/// `values[0]` predicts within ±`epsilon`.
+/// Returns `(len, slope)` with the slope in 32-bit fixed point.
+/// `values` 中能被一条过 `values[0]` 的直线在 ±`epsilon` 内预测的最长前缀
+/// （至多 `max_len`）。返回 `(长度, 斜率)`，斜率为 32 位定点数。
+pub(crate) fn fit<T: PcInt>(values: &[T], epsilon: u64, max_len: usize) -> (usize, i128) {
+  let n = values.len().min(max_len);
+  if n <= 1 {
+    return (n, 0);
+  }
+
+  let y0 = values[0].to_i128();
+  let eps = epsilon as i128;
+  // Slope bounds lo_num / lo_den <= s <= hi_num / hi_den, denominators > 0
+  // 斜率边界 lo_num / lo_den <= s <= hi_num / hi_den，分母 > 0
+  let (mut lo_num, mut lo_den) = (i128::MIN, 1i128);
+  let (mut hi_num, mut hi_den) = (i128::MAX, 1i128);
+  let mut len = 1;
+
+  for (x, &y) in values[..n].iter().enumerate().skip(1) {
+    let x = x as i128;
+    let dy = y.to_i128() - y0;
+    let (lo, hi) = (dy - eps, dy + eps);
+
+    // Reject when the new interval misses the current one
+    // 新区间与当前区间不相交则拒绝
+    let lo_past_hi = hi_num != i128::MAX && lo * hi_den > hi_num * x;
+    let hi_below_lo = lo_num != i128::MIN && hi * lo_den < lo_num * x;
+    if lo_past_hi || hi_below_lo {
+      break;
+    }
+    if lo_num == i128::MIN || lo * lo_den > lo_num * x {
+      (lo_num, lo_den) = (lo, x);
+    }
+    if hi_num == i128::MAX || hi * hi_den < hi_num * x {
+      (hi_num, hi_den) = (hi, x);
+    }
+    len += 1;
+  }
+
+  if len == 1 {
+    return (1, 0);
+  }
+  // Midpoint of the bounds, rounded to nearest so an exact integer slope
+  // survives the fixed-point conversion
+  // 取边界中点并就近舍入，使精确的整数斜率经定点转换后不变
+  let num = (lo_num * hi_den + hi_num * lo_den) << 32;
+  let den = 2 * lo_den * hi_den;
+  (len, (num + den / 2).div_euclid(den))
+}
@@ -0,0 +1,269 @@
+//! Block encoder: residuals, PFOR width choice and exceptions
+//! 块编码器：残差、PFOR 位宽选择与异常值
+//!
+//! Codes wider than the block's width are exceptions. A block with
+//! exceptions owns one section of the exception stream: their in-block
+//! positions at `pos_width(len)` bits each, then their codes at the block's
+//! own `ex_width`. An exception's residual slot holds its rank within the
+//! section, so `slot < ex_count && position[slot] == k` identifies it in O(1).
+//! 宽于块位宽的码为异常值。含异常值的块在异常流中占一段：先是各异常的块内
+//! 位置（每个 `pos_width(len)` 位），再是按该块自身 `ex_width` 存放的码。
+//! 异常值的残差槽存其在段内的秩，因此 `slot < ex_count && position[slot] == k`
+//! 即可 O(1) 识别。
+
+use std::marker::PhantomData;
+
+use jdb_pgm_lib::bits::BitWriter;
+
+use super::types::{BlockMeta, FLAG_HAS_EX, PcInt, max_ex, pos_width};
+
+/// Whether `code` fits in `w` bits.
+/// `code` 能否放进 `w` 位。
+#[inline(always)]
+pub(crate) fn fits(code: u64, w: u8) -> bool {
+  w >= 64 || code >> w == 0
+}
+
+/// Anchors for blocks holding absolute u32 offsets: each superblock of
+/// `1 << super_shift` blocks is rebased onto its first block's offsets.
+/// 为存绝对 u32 偏移的块生成锚点：每个含 `1 << super_shift` 块的超块改为
+/// 相对其首块的偏移。
+pub(crate) fn anchor(block_meta: &mut [BlockMeta], super_shift: u32) -> Vec<u64> {
+  let mut anchors = Vec::with_capacity(block_meta.len().div_ceil(1 << super_shift) * 2);
+  for chunk in block_meta.chunks_mut(1 << super_shift) {
+    let (bit, ex) = (chunk[0].bit_offset, chunk[0].exception_offset);
+    anchors.extend([bit as u64, ex as u64]);
+    for meta in chunk {
+      meta.bit_offset -= bit;
+      meta.exception_offset -= ex;
+    }
+  }
+  anchors
+}
+
+/// Accumulates encoded blocks in index order.
+/// 按索引顺序累积已编码的块。
+pub(crate) struct Enc<T: PcInt> {
+  pub block_meta: Vec<BlockMeta>,
+  pub bit_writer: BitWriter,
+  /// Exception sections, one per block that has exceptions
+  /// 异常段，每个含异常值的块一段
+  pub ex_writer: BitWriter,
+  /// Large mode: absolute (bit, exception) offsets of each superblock
+  /// 大模式：各超块的绝对（位、异常）偏移
+  pub anchors: Vec<u64>,
+  pub large: bool,
+  super_shift: u32,
+  diff_buffer: Vec<u64>,
+  ex_penalty: u64,
+  elem: PhantomData<T>,
+}
+
+impl<T: PcInt> Enc<T> {
+  pub fn new(n: usize, ex_penalty: u8, super_shift: u32, large: bool) -> Self {
+    Self {
+      block_meta: Vec::new(),
+      bit_writer: BitWriter::with_capacity(n),
+      ex_writer: BitWriter::with_capacity(0),
+      anchors: Vec::new(),
+      large,
+      super_shift,
+      diff_buffer: Vec::new(),
+      ex_penalty: ex_penalty as u64,
+      elem: PhantomData,
+    }
+  }
+
+  /// Offsets of the next block as stored in its meta, anchoring a new
+  /// superblock or switching to large mode when needed.
+  /// 下一个块存入元数据的偏移；需要时开启新超块或切换到大模式。
+  fn next_offsets(&mut self) -> (u32, u32) {
+    let bit = self.bit_writer.current_bit_offset() as u64;
+    let ex = self.ex_writer.current_bit_offset() as u64;
+    if !self.large && (bit > u32::MAX as u64 || ex > u32::MAX as u64) {
+      self.promote();
+    }
+    if !self.large {
+      return (bit as u32, ex as u32);
+    }
+    if self.block_meta.len() & ((1 << self.super_shift) - 1) == 0 {
+      self.anchors.extend([bit, ex]);
+    }
+    let at = self.anchors.len() - 2;
+    let rel = |abs: u64, anchor: u64| {
+      u32::try_from(abs - anchor).expect("super_shift bounds offsets within a superblock")
+    };
+    (rel(bit, self.anchors[at]), rel(ex, self.anchors[at + 1]))
+  }
+
+  /// Append an already encoded block of `len` values unchanged: its model,
+  /// residual bits from `src` and exception section from `ex`, each given
+  /// as words and a bit offset.
+  /// 原样追加一个已编码的 `len` 个值的块：模型、来自 `src` 的残差位及来自
+  /// `ex` 的异常段，均以字数组与位偏移给出。
+  pub fn push_encoded(
+    &mut self,
+    meta: &BlockMeta,
+    src: (&[u64], usize),
+    ex: (&[u64], usize),
+    len: usize,
+  ) {
+    let (bit_offset, exception_offset) = self.next_offsets();
+    self
+      .bit_writer
+      .write_run(src.0, src.1, len * meta.bit_width as usize);
+    self.ex_writer.write_run(ex.0, ex.1, meta.ex_bits(len));
+    self.block_meta.push(BlockMeta {
+      bit_offset,
+      exception_offset,
+      ..*meta
+    });
+  }
+
+  /// Rebase the blocks written so far onto superblock anchors.
+  /// 将已写出的块改为相对超块锚点。
+  pub fn promote(&mut self) {
+    self.large = true;
+    self.anchors = anchor(&mut self.block_meta, self.super_shift);
+  }
+
+  /// Model and zigzag residual codes (into `diff_buffer`) of `block_data`
+  /// on a line through its first element with slope `slope_q32`.
+  /// `block_data` 在过首元素、斜率为 `slope_q32` 的直线上的模型与 zigzag
+  /// 残差码（写入 `diff_buffer`）。
+  fn model(&mut self, block_data: &[T], slope_q32: i128) -> BlockMeta {
+    let (slope, shift) = quantize(slope_q32, block_data.len() - 1);
+    let meta = BlockMeta {
+      base: block_data[0].to_bits(),
+      slope,
+      shift,
+      ..BlockMeta::default()
+    };
+
+    let diff_buffer = &mut self.diff_buffer;
+    diff_buffer.clear();
+    for (k, &val) in block_data.iter().enumerate() {
+      // Wrap to the native width so the code never exceeds T::BITS
+      // 回绕到原生宽度，使编码不超过 T::BITS 位
+      let diff = T::narrow(val.to_bits().wrapping_sub(meta.predict(k)));
+      // ZigZag encode
+      diff_buffer.push(((diff as u64) << 1) ^ ((diff >> 63) as u64));
+    }
+    meta
+  }
+
+  /// PFOR cost of `len` values at width `w` with `num_ex` exceptions of
+  /// `ex_w` bits each.
+  /// 宽度 `w`、含 `num_ex` 个 `ex_w` 位异常值的 `len` 个值的 PFOR 代价。
+  #[inline]
+  pub fn cost(&self, len: usize, w: u8, num_ex: usize, ex_w: u8) -> u64 {
+    let ex_bits = (pos_width(len) + ex_w) as u64;
+    len as u64 * w as u64 + num_ex as u64 * ex_bits * self.ex_penalty
+  }
+
+  /// Width with the lowest cost for the codes in `diff_buffer`, its cost
+  /// and the width of the exception codes.
+  /// `diff_buffer` 中编码代价最低的位宽、其代价及异常码的位宽。
+  fn best_width(&self) -> (u8, u64, u8) {
+    let mut best_w = 0;
+    let mut min_cost = u64::MAX;
+    let bits = T::BITS as usize;
+    let len = self.diff_buffer.len();
+    let mut counts = [0usize; 65];
+    for &d in self.diff_buffer.iter() {
+      counts[if d == 0 {
+        0
+      } else {
+        64 - d.leading_zeros() as usize
+      }] += 1;
+    }
+    // Exceptions, if any, include the widest code
+    // 若有异常值，其中必含最宽的码
+    let ex_w = counts.iter().rposition(|&c| c > 0).unwrap_or(0) as u8;
+    let mut num_ex = 0;
+    for w in (0..=bits).rev() {
+      num_ex += if w < bits { counts[w + 1] } else { 0 };
+      if num_ex > max_ex(w as u8) {
+        break;
+      }
+      let cost = self.cost(len, w as u8, num_ex, ex_w);
+      if cost <= min_cost {
+        min_cost = cost;
+        best_w = w as u8;
+      }
+    }
+    (best_w, min_cost, ex_w)
+  }
+
+  /// Cost `push` would spend on `block_data`, without writing anything.
+  /// `push` 编码 `block_data` 的代价，不写出任何内容。
+  pub fn trial(&mut self, block_data: &[T], slope_q32: i128) -> u64 {
+    self.model(block_data, slope_q32);
+    self.best_width().1
+  }
+
+  /// Encode `block_data` on a line through its first element with slope
+  /// `slope_q32` (32 fractional bits).
+  /// 以过首元素、斜率为 `slope_q32`（32 位小数）的直线编码 `block_data`。
+  pub fn push(&mut self, block_data: &[T], slope_q32: i128) {
+    let (bit_offset, exception_offset) = self.next_offsets();
+    let mut meta = self.model(block_data, slope_q32);
+    let (w, _, ex_w) = self.best_width();
+    let pw = pos_width(block_data.len());
+
+    // Slots and positions first, then the exception codes
+    // 先写槽位与位置，再写异常码
+    let mut num_ex = 0u64;
+    for (k, &code) in self.diff_buffer.iter().enumerate() {
+      let slot = if fits(code, w) {
+        code
+      } else {
+        self.ex_writer.write(k as u64, pw);
+        num_ex += 1;
+        num_ex - 1
+      };
+      self.bit_writer.write(slot, w);
+    }
+    if num_ex > 0 {
+      for &code in self.diff_buffer.iter().filter(|&&c| !fits(c, w)) {
+        self.ex_writer.write(code, ex_w);
+      }
+      meta.flags = FLAG_HAS_EX;
+      meta.ex_width = ex_w;
+      meta.ex_count = num_ex as u16;
+    }
+
+    meta.bit_offset = bit_offset;
+    meta.bit_width = w;
+    meta.exception_offset = exception_offset;
+    self.block_meta.push(meta);
+  }
+}
+
+/// Slope (32 fractional bits) of the line from the first value to the last.
+/// 首值到末值直线的斜率（32 位小数）。
+pub(crate) fn line_slope<T: PcInt>(data: &[T]) -> i128 {
+  let len = data.len();
+  if len < 2 {
+    return 0;
+  }
+  let first = data[0].to_bits();
+  let last = data[len - 1].to_bits();
+  ((T::narrow(last.wrapping_sub(first)) as i128) << 32) / (len as i128 - 1)
+}
+
+/// Fit a 32-fraction-bit slope into i64 with as many fractional bits as
+/// keep `span * slope` clear of overflow. Past that the slope is an integer
+/// taken mod 2^64, where wrapping multiplication is exact.
+/// 将 32 位小数的斜率装入 i64，在 `span * slope` 不溢出的前提下保留尽量多的
+/// 小数位。超出时斜率取整并按 2^64 取模，此时回绕乘法是精确的。
+fn quantize(slope_q32: i128, span: usize) -> (i64, u8) {
+  let span = span.max(1) as u128;
+  for shift in (1..=32u8).rev() {
+    let slope = slope_q32 >> (32 - shift);
+    if slope.unsigned_abs().saturating_mul(span) < 1 << 62 {
+      return (slope as i64, shift);
+    }
+  }
+  ((slope_q32 >> 32) as i64, 0)
+}
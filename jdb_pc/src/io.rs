@@ -1,66 +1,455 @@
-use super::{PcBase, types::BlockMeta};
+//! Pc serialization
+//! Pc 序列化
+//!
+//! Layout (little endian, every section starts 8-byte aligned):
+//! 布局（小端，每段均 8 字节对齐）：
+//!
+//! ```text
+//! magic "JDPC" | version u8 | mode u8 | dir_shift u8 | elem u8
+//! block_len u32 | flags u16 | ex_penalty u8 | 0 u8
+//! epsilon | len | blocks | residuals | exceptions | starts | dir   (u64 each)
+//! block_meta: 32-byte records laid out as the repr(C) BlockMeta
+//! residuals, exceptions, starts: u64 words
+//! dir: u32 entries, zero-padded to 8 bytes
+//! anchors: u64 (bit, exception) pairs, one per superblock, only with FLAG_LARGE
+//! crc32 of everything above | 0u32
+//! ```
+//!
+//! Bytes without the magic are read with the header-less layout that
+//! predates it: fixed blocks of u64 on a global line (unsigned slope and
+//! intercept over absolute indices), exception codes flagged by a bitmap.
+//! They are decoded and re-encoded on `load` and cannot be viewed in place.
+//! 不带魔数的字节按此前的无文件头布局读取：u64 固定分块，块直线为全局的
+//! （基于绝对索引的无符号斜率与截距），异常码由位图标记。它们在 `load` 时
+//! 解码后重新编码，无法原地视图。
+
+use std::{marker::PhantomData, mem::size_of, slice};
+
+use jdb_pgm_lib::{
+  bits::read_bits,
+  error::{PgmError, Result},
+};
+
+use super::{
+  PcBase, PcViewBase,
+  types::{BlockMeta, BlockMode, ExPenalty, FLAG_HAS_EX, PcConf, PcInt, pos_width, super_shift},
+};
+
+pub const MAGIC: [u8; 4] = *b"JDPC";
+pub const VERSION: u8 = 1;
+/// Fixed header size; sections follow
+/// 固定文件头长度，其后为各段
+pub const HEAD_LEN: usize = 72;
+/// Trailing crc32 plus padding
+/// 尾部 crc32 及填充
+pub const CRC_LEN: usize = 8;
+/// Header flag: block offsets are relative to superblock anchors
+/// 文件头标志：块偏移相对于超块锚点
+pub const FLAG_LARGE: u16 = 1;
+/// Header flags this build understands
+/// 本版本能识别的文件头标志
+const KNOWN_FLAGS: u16 = FLAG_LARGE;
+const META_LEN: usize = 32;
+
+const _: () = assert!(size_of::<BlockMeta>() == META_LEN);
+
+/// Append words as little-endian bytes
+/// 以小端字节追加字
+fn put_words(out: &mut Vec<u8>, words: &[u64]) {
+  if cfg!(target_endian = "little") {
+    // SAFETY: u64 has no padding and matches the LE byte order here
+    out.extend_from_slice(unsafe {
+      slice::from_raw_parts(words.as_ptr().cast::<u8>(), size_of_val(words))
+    });
+  } else {
+    for w in words {
+      out.extend_from_slice(&w.to_le_bytes());
+    }
+  }
+}
 
 /// Serialize Pc to bytes
 /// 序列化 Pc 为字节流
-pub fn dump<const B: usize>(pc: &PcBase<B>) -> Vec<u8> {
-  let mut out = Vec::with_capacity(pc.size_in_bytes());
-  out.extend_from_slice(&(pc.len as u64).to_le_bytes());
-
-  // Helper macro for vector serialization
-  macro_rules! serialize_vec {
-    ($vec:expr, $serializer:expr) => {
-      out.extend_from_slice(&($vec.len() as u32).to_le_bytes());
-      for item in &$vec {
-        $serializer(item);
-      }
-    };
+pub fn dump<const B: usize, T: PcInt>(pc: &PcBase<B, T>) -> Vec<u8> {
+  let mut out = Vec::with_capacity(HEAD_LEN + pc.size_in_bytes() + CRC_LEN);
+  out.extend_from_slice(&MAGIC);
+  out.extend_from_slice(&[VERSION, pc.mode as u8, pc.dir_shift, T::TAG]);
+  out.extend_from_slice(&(B as u32).to_le_bytes());
+  let flags = if pc.is_large() { FLAG_LARGE } else { 0 };
+  out.extend_from_slice(&flags.to_le_bytes());
+  out.extend_from_slice(&[pc.ex_penalty.get(), 0]);
+  for n in [
+    pc.epsilon,
+    pc.len,
+    pc.block_meta.len(),
+    pc.residuals.len(),
+    pc.exceptions.len(),
+    pc.starts.len(),
+    pc.dir.len(),
+  ] {
+    out.extend_from_slice(&(n as u64).to_le_bytes());
   }
 
-  serialize_vec!(pc.block_meta, |b: &BlockMeta| {
+  for b in &pc.block_meta {
     out.extend_from_slice(&b.bit_offset.to_le_bytes());
-    out.push(b.bit_width);
-    out.push(b.flags);
     out.extend_from_slice(&b.exception_offset.to_le_bytes());
-    // New fields
-    out.extend_from_slice(&b.slope_fp.to_le_bytes());
-    out.extend_from_slice(&b.intercept_fp.to_le_bytes());
-  });
+    out.extend_from_slice(&b.base.to_le_bytes());
+    out.extend_from_slice(&b.slope.to_le_bytes());
+    out.extend_from_slice(&[b.bit_width, b.flags, b.shift, b.ex_width]);
+    out.extend_from_slice(&b.ex_count.to_le_bytes());
+    out.extend_from_slice(&[0, 0]);
+  }
+  put_words(&mut out, &pc.residuals);
+  put_words(&mut out, &pc.exceptions);
+  put_words(&mut out, &pc.starts);
+  for d in &pc.dir {
+    out.extend_from_slice(&d.to_le_bytes());
+  }
+  out.resize(out.len().next_multiple_of(8), 0);
+  put_words(&mut out, &pc.anchors);
+  let crc = crc32fast::hash(&out);
+  out.extend_from_slice(&crc.to_le_bytes());
+  out.extend_from_slice(&0u32.to_le_bytes());
+  out
+}
 
-  // Optimized serialization for u64 vectors (bulk copy on little endian)
-  // 针对 u64 向量的优化序列化（小端序批量复制）
-  if cfg!(target_endian = "little") {
-    let mut serialize_u64_fast = |vec: &[u64]| {
-      out.extend_from_slice(&(vec.len() as u32).to_le_bytes());
-      // SAFETY: u64 slice cast to u8 slice is safe for POD.
-      // On LE, memory layout matches.
-      unsafe {
-        let ptr = vec.as_ptr() as *const u8;
-        let len = vec.len() * 8;
-        let slice = std::slice::from_raw_parts(ptr, len);
-        out.extend_from_slice(slice);
+/// Deserialize Pc from bytes (any alignment, any layout, any endianness).
+/// 从字节流反序列化 Pc（任意对齐、任意布局、任意字节序）。
+pub fn load<const B: usize, T: PcInt>(bytes: &[u8]) -> Result<PcBase<B, T>> {
+  if !bytes.starts_with(&MAGIC) {
+    return load_v0(bytes);
+  }
+  if cfg!(target_endian = "big") {
+    return load_copy(bytes);
+  }
+  let load_aligned = |bytes: &[u8]| parse::<B, T>(bytes, true).map(|pc| pc.to_pc());
+  if bytes.as_ptr().align_offset(8) == 0 {
+    return load_aligned(bytes);
+  }
+  // Realign once, then reuse the zero-copy parser
+  // 重新对齐一次，再复用零拷贝解析
+  let mut words = vec![0u64; bytes.len().div_ceil(8)];
+  // SAFETY: words spans at least bytes.len() bytes
+  let aligned = unsafe {
+    std::ptr::copy_nonoverlapping(bytes.as_ptr(), words.as_mut_ptr().cast::<u8>(), bytes.len());
+    slice::from_raw_parts(words.as_ptr().cast::<u8>(), bytes.len())
+  };
+  load_aligned(aligned)
+}
+
+/// Borrow sections out of 8-byte aligned bytes written by `dump`, after
+/// checking the CRC.
+/// 校验 CRC 后，从 `dump` 写出的 8 字节对齐字节中借用各段。
+pub fn view<const B: usize, T: PcInt>(bytes: &[u8]) -> Result<PcViewBase<'_, B, T>> {
+  parse(bytes, true)
+}
+
+/// As `view`, but skips the CRC pass; structure is still validated.
+/// 同 `view`，但跳过 CRC 计算；结构仍会校验。
+pub fn view_skip_crc<const B: usize, T: PcInt>(bytes: &[u8]) -> Result<PcViewBase<'_, B, T>> {
+  parse(bytes, false)
+}
+
+/// Deserialize Pc by decoding every section with `from_le_bytes`; works on
+/// any target and alignment, at the cost of a copy.
+/// 用 `from_le_bytes` 逐段解码反序列化 Pc；适用于任意目标与对齐，代价是一次复制。
+pub fn load_copy<const B: usize, T: PcInt>(bytes: &[u8]) -> Result<PcBase<B, T>> {
+  let h = head::<B, T>(bytes, true)?;
+  let section = |i: usize| &bytes[h.offsets[i]..h.offsets[i + 1]];
+  let words = |i: usize, n: usize| -> Vec<u64> {
+    section(i)
+      .chunks_exact(8)
+      .take(n)
+      .map(|c| u64::from_le_bytes(c.try_into().unwrap()))
+      .collect()
+  };
+  let block_meta = section(0)
+    .chunks_exact(META_LEN)
+    .map(|c| {
+      let u32_at = |at: usize| u32::from_le_bytes(c[at..at + 4].try_into().unwrap());
+      let u64_at = |at: usize| u64::from_le_bytes(c[at..at + 8].try_into().unwrap());
+      BlockMeta {
+        bit_offset: u32_at(0),
+        exception_offset: u32_at(4),
+        base: u64_at(8),
+        slope: u64_at(16) as i64,
+        bit_width: c[24],
+        flags: c[25],
+        shift: c[26],
+        ex_width: c[27],
+        ex_count: u16::from_le_bytes([c[28], c[29]]),
       }
+    })
+    .collect();
+  let pc = PcBase {
+    block_meta,
+    residuals: words(1, h.residuals),
+    exceptions: words(2, h.exceptions),
+    mode: h.mode,
+    epsilon: h.epsilon,
+    ex_penalty: h.ex_penalty,
+    starts: words(3, h.starts),
+    dir: section(4)
+      .chunks_exact(4)
+      .take(h.dir)
+      .map(|c| u32::from_le_bytes(c.try_into().unwrap()))
+      .collect(),
+    dir_shift: h.dir_shift,
+    anchors: words(5, h.anchors),
+    len: h.len,
+    elem: PhantomData,
+  };
+  validate(&pc.view())?;
+  Ok(pc)
+}
+
+fn parse<const B: usize, T: PcInt>(bytes: &[u8], check_crc: bool) -> Result<PcViewBase<'_, B, T>> {
+  let err = |msg: &str| Err(PgmError::InvalidData(msg.into()));
+  if cfg!(target_endian = "big") {
+    return err("Zero-copy view needs a little-endian target");
+  }
+  if bytes.as_ptr().align_offset(8) != 0 {
+    return err("Pc bytes must start 8-byte aligned");
+  }
+  let h = head::<B, T>(bytes, check_crc)?;
+
+  // SAFETY: every section lies inside `bytes`, starts 8-byte aligned and
+  // holds plain integers, for which any bit pattern is valid
+  let pc = unsafe {
+    let at = |i: usize| bytes.as_ptr().add(h.offsets[i]);
+    PcViewBase {
+      block_meta: slice::from_raw_parts(at(0).cast::<BlockMeta>(), h.blocks),
+      residuals: slice::from_raw_parts(at(1).cast::<u64>(), h.residuals),
+      exceptions: slice::from_raw_parts(at(2).cast::<u64>(), h.exceptions),
+      mode: h.mode,
+      epsilon: h.epsilon,
+      ex_penalty: h.ex_penalty,
+      starts: slice::from_raw_parts(at(3).cast::<u64>(), h.starts),
+      dir: slice::from_raw_parts(at(4).cast::<u32>(), h.dir),
+      dir_shift: h.dir_shift,
+      anchors: slice::from_raw_parts(at(5).cast::<u64>(), h.anchors),
+      len: h.len,
+      elem: PhantomData,
+    }
+  };
+  validate(&pc)?;
+  Ok(pc)
+}
+
+/// Header fields, section counts and section byte offsets
+/// 文件头字段、各段元素数与字节偏移
+struct Head {
+  mode: BlockMode,
+  dir_shift: u8,
+  ex_penalty: ExPenalty,
+  epsilon: usize,
+  len: usize,
+  blocks: usize,
+  residuals: usize,
+  exceptions: usize,
+  starts: usize,
+  dir: usize,
+  anchors: usize,
+  offsets: [usize; 7],
+}
+
+/// Check the header and CRC and locate the sections inside `bytes`
+/// 校验文件头与 CRC，并定位 `bytes` 中的各段
+fn head<const B: usize, T: PcInt>(bytes: &[u8], check_crc: bool) -> Result<Head> {
+  let err = |msg: String| Err(PgmError::InvalidData(msg));
+  if bytes.len() < HEAD_LEN + CRC_LEN || bytes[..4] != MAGIC {
+    return err("Missing Pc header".into());
+  }
+  let u32_at = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+
+  let body_end = bytes.len() - CRC_LEN;
+  if check_crc && crc32fast::hash(&bytes[..body_end]) != u32_at(body_end) {
+    return err("Pc checksum mismatch".into());
+  }
+  if bytes[4] != VERSION {
+    return err(format!("Unsupported Pc version {}", bytes[4]));
+  }
+  let Some(mode) = BlockMode::from_u8(bytes[5]) else {
+    return err("Unknown block mode".into());
+  };
+  let dir_shift = bytes[6];
+  if bytes[7] != T::TAG {
+    return err(format!(
+      "Element type {:#x}, expected {:#x}",
+      bytes[7],
+      T::TAG
+    ));
+  }
+  let block_len = u32_at(8);
+  if block_len as usize != B {
+    return err(format!("Block length {block_len}, expected {B}"));
+  }
+  let flags = u16::from_le_bytes([bytes[12], bytes[13]]);
+  if flags & !KNOWN_FLAGS != 0 {
+    return err(format!("Unknown Pc flags {flags:#x}"));
+  }
+  let ex_penalty = bytes[14];
+  if !(ExPenalty::MIN..=ExPenalty::MAX).contains(&ex_penalty) || bytes[15] != 0 {
+    return err(format!("Invalid exception penalty {ex_penalty}"));
+  }
+
+  let mut counts = [0usize; 7];
+  for (i, c) in counts.iter_mut().enumerate() {
+    let at = 16 + i * 8;
+    let n = u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap());
+    let Ok(n) = usize::try_from(n) else {
+      return err("Section length overflows usize".into());
     };
-    serialize_u64_fast(&pc.residuals);
-    serialize_u64_fast(&pc.exceptions);
-    serialize_u64_fast(&pc.bitmap);
+    *c = n;
+  }
+  let [epsilon, len, blocks, residuals, exceptions, starts, dir] = counts;
+  let anchors = if flags & FLAG_LARGE != 0 {
+    blocks.div_ceil(1 << super_shift(B)) * 2
   } else {
-    serialize_vec!(pc.residuals, |r: &u64| out
-      .extend_from_slice(&r.to_le_bytes()));
-    serialize_vec!(pc.exceptions, |e: &u64| out
-      .extend_from_slice(&e.to_le_bytes()));
-    serialize_vec!(pc.bitmap, |b: &u64| out.extend_from_slice(&b.to_le_bytes()));
+    0
+  };
+
+  // Section byte sizes, checked against overflow and the buffer
+  // 各段字节数，检查溢出并与缓冲区核对
+  let sizes = [
+    blocks.checked_mul(META_LEN),
+    residuals.checked_mul(8),
+    exceptions.checked_mul(8),
+    starts.checked_mul(8),
+    dir.checked_mul(4).map(|n| n.next_multiple_of(8)),
+    Some(anchors * 8),
+  ];
+  let mut offsets = [0usize; 7];
+  offsets[0] = HEAD_LEN;
+  for (i, size) in sizes.iter().enumerate() {
+    let Some(end) = size.and_then(|n| offsets[i].checked_add(n)) else {
+      return err("Section length overflows usize".into());
+    };
+    offsets[i + 1] = end;
+  }
+  if offsets[6] != body_end {
+    return err("Section lengths do not match the buffer".into());
   }
+  Ok(Head {
+    mode,
+    dir_shift,
+    ex_penalty: ExPenalty::new(ex_penalty),
+    epsilon,
+    len,
+    blocks,
+    residuals,
+    exceptions,
+    starts,
+    dir,
+    anchors,
+    offsets,
+  })
+}
 
-  out
+/// Check that the block count, and in epsilon mode the block starts and the
+/// directory, agree with `len`.
+/// 检查块数（epsilon 模式下还有块首与目录）与 `len` 一致。
+fn check_layout<const B: usize>(
+  mode: BlockMode,
+  len: usize,
+  blocks: usize,
+  starts: &[u64],
+  dir: &[u32],
+  dir_shift: u8,
+) -> Result<()> {
+  let bad = |msg: &str| Err(PgmError::InvalidData(msg.into()));
+  match mode {
+    BlockMode::Fixed => {
+      if blocks != len.div_ceil(B) || !starts.is_empty() || !dir.is_empty() {
+        return bad("Block count does not match len and block length");
+      }
+    }
+    BlockMode::Epsilon => {
+      if len > 0 {
+        let sorted = starts.windows(2).all(|w| w[0] < w[1]);
+        let ok = starts.len() == blocks + 1
+          && starts.first() == Some(&0)
+          && starts.last() == Some(&(len as u64))
+          && sorted
+          && dir_shift < 64
+          && dir.len() == ((len - 1) >> dir_shift) + 1
+          && dir.iter().enumerate().all(|(k, &b)| {
+            (b as usize) < blocks && starts[b as usize] <= ((k as u64) << dir_shift)
+          });
+        if !ok {
+          return bad("Inconsistent epsilon block directory");
+        }
+      } else if blocks != 0 {
+        return bad("Blocks present in an empty Pc");
+      }
+    }
+  }
+  Ok(())
+}
+
+/// Check everything `get_unchecked` and the iterators rely on, in one pass
+/// over the block list: block layout, residual bit ranges, exception
+/// sections and the epsilon directory.
+/// 一次遍历块列表，检查 `get_unchecked` 与迭代器依赖的一切：分块布局、
+/// 残差位区间、异常段以及 epsilon 目录。
+pub(crate) fn validate<const B: usize, T: PcInt>(pc: &PcViewBase<'_, B, T>) -> Result<()> {
+  let bad = |msg: &str| Err(PgmError::InvalidData(msg.into()));
+  let blocks = pc.block_meta.len();
+  check_layout::<B>(pc.mode, pc.len, blocks, pc.starts, pc.dir, pc.dir_shift)?;
+  if pc.len == 0 {
+    return Ok(());
+  }
+
+  // Decoders may read one padding word past the last residual
+  // 解码可能越过最后一个残差读取一个填充字
+  let residual_bits = pc.residuals.len() as u128 * 64;
+  let ex_bits = pc.exceptions.len() as u128 * 64;
+  if !pc.anchors.is_empty() {
+    // Anchors within their sections keep `offsets` from overflowing
+    // 锚点不越出各自的段，`offsets` 便不会溢出
+    let ok = pc.anchors.len() == blocks.div_ceil(1 << super_shift(B)) * 2
+      && pc
+        .anchors
+        .chunks(2)
+        .all(|a| a[0] as u128 <= residual_bits && a[1] as u128 <= ex_bits);
+    if !ok {
+      return bad("Superblock anchors out of range");
+    }
+  }
+  for (b_idx, meta) in pc.block_meta.iter().enumerate() {
+    let (start, end) = pc.block_span(b_idx);
+    let len = end - start;
+    let w = meta.bit_width as u128;
+    if meta.shift > 32 {
+      return bad("Slope shift out of range");
+    }
+    let (bit_off, ex_off) = pc.offsets(b_idx, meta);
+    if w > 64 || bit_off as u128 + len as u128 * w + 64 > residual_bits {
+      return bad("Residual bits out of range");
+    }
+    let n = meta.ex_count as u128;
+    let per_ex = pos_width(len) as u128 + meta.ex_width as u128;
+    if meta.ex_width > 64 || n > len as u128 || ex_off as u128 + n * per_ex > ex_bits {
+      return bad("Exception section out of range");
+    }
+  }
+  Ok(())
 }
 
-/// Deserialize Pc from bytes.
-/// 从字节流反序列化 Pc。
-pub fn load<const B: usize>(bytes: &[u8]) -> jdb_pgm_lib::error::Result<PcBase<B>> {
+/// Deserialize Pc from the unaligned layout that predates the header.
+/// 从无文件头的旧版非对齐布局反序列化 Pc。
+pub fn load_v0<const B: usize, T: PcInt>(bytes: &[u8]) -> Result<PcBase<B, T>> {
+  // Only u64 columns were written before the header existed
+  // 文件头出现之前只写过 u64 列
+  if T::TAG != u64::TAG {
+    return Err(PgmError::InvalidData(
+      "Header-less Pc bytes hold u64 elements".into(),
+    ));
+  }
   let mut pos = 0;
 
   if bytes.len() < 8 {
-    return Err(jdb_pgm_lib::error::PgmError::InvalidData(
+    return Err(PgmError::InvalidData(
       "Data too short for length header".into(),
     ));
   }
@@ -70,7 +459,7 @@ pub fn load<const B: usize>(bytes: &[u8]) -> jdb_pgm_lib::error::Result<PcBase<B
   macro_rules! check_len {
     ($needed:expr) => {
       if pos + $needed > bytes.len() {
-        return Err(jdb_pgm_lib::error::PgmError::InvalidData(format!(
+        return Err(PgmError::InvalidData(format!(
           "Unexpected EOF at pos {}, needed {}",
           pos, $needed
         )));
@@ -106,30 +495,33 @@ pub fn load<const B: usize>(bytes: &[u8]) -> jdb_pgm_lib::error::Result<PcBase<B
     }};
   }
 
-  let block_meta = deserialize_vec!(|| -> jdb_pgm_lib::error::Result<BlockMeta> {
+  let block_meta = deserialize_vec!(|| -> Result<BlockMeta> {
     let bit_offset = read_u32!();
     check_len!(2); // bit_width + flags
     let bit_width = bytes[pos];
     let flags = bytes[pos + 1];
     pos += 2;
     let exception_offset = u32::from_le_bytes(read_slice!(4).try_into().unwrap());
-    let slope_fp = read_u64!();
-    let intercept_fp = i64::from_le_bytes(read_slice!(8).try_into().unwrap());
+    // Global slope_fp and intercept_fp, read back by `v0_values`
+    // 全局 slope_fp 与 intercept_fp，由 `v0_values` 读取
+    let base = read_u64!();
+    let slope = i64::from_le_bytes(read_slice!(8).try_into().unwrap());
 
     Ok(BlockMeta {
       bit_offset,
+      exception_offset,
+      base,
+      slope,
       bit_width,
       flags,
-      exception_offset,
-      slope_fp,
-      intercept_fp,
+      ..BlockMeta::default()
     })
   });
 
-  let load_u64_vec = |pos: &mut usize| -> jdb_pgm_lib::error::Result<Vec<u64>> {
+  let load_u64_vec = |pos: &mut usize| -> Result<Vec<u64>> {
     let p = *pos;
     if p + 4 > bytes.len() {
-      return Err(jdb_pgm_lib::error::PgmError::InvalidData(format!(
+      return Err(PgmError::InvalidData(format!(
         "EOF reading vec len at {}",
         p
       )));
@@ -141,7 +533,7 @@ pub fn load<const B: usize>(bytes: &[u8]) -> jdb_pgm_lib::error::Result<PcBase<B
     if cfg!(target_endian = "little") {
       let byte_len = count * 8;
       if *pos + byte_len > bytes.len() {
-        return Err(jdb_pgm_lib::error::PgmError::InvalidData(format!(
+        return Err(PgmError::InvalidData(format!(
           "EOF reading vec body at {}, needed {}",
           *pos, byte_len
         )));
@@ -160,9 +552,7 @@ pub fn load<const B: usize>(bytes: &[u8]) -> jdb_pgm_lib::error::Result<PcBase<B
       let mut vec = Vec::with_capacity(count);
       for _ in 0..count {
         if *pos + 8 > bytes.len() {
-          return Err(jdb_pgm_lib::error::PgmError::InvalidData(
-            "EOF reading u64".into(),
-          ));
+          return Err(PgmError::InvalidData("EOF reading u64".into()));
         }
         let val = u64::from_le_bytes(bytes[*pos..*pos + 8].try_into().unwrap());
         *pos += 8;
@@ -175,12 +565,70 @@ pub fn load<const B: usize>(bytes: &[u8]) -> jdb_pgm_lib::error::Result<PcBase<B
   let residuals = load_u64_vec(&mut pos)?;
   let exceptions = load_u64_vec(&mut pos)?;
   let bitmap = load_u64_vec(&mut pos)?;
+  if pos != bytes.len() {
+    return Err(PgmError::InvalidData(format!(
+      "Trailing bytes after Pc at {pos}"
+    )));
+  }
 
-  Ok(PcBase {
-    block_meta,
-    residuals,
-    exceptions,
-    bitmap,
-    len,
-  })
+  let values: Vec<T> = v0_values::<B>(len, &block_meta, &residuals, &exceptions, &bitmap)?
+    .into_iter()
+    .map(T::from_bits)
+    .collect();
+  // Keep the fixed blocks these bytes were written with
+  // 保留这些字节写入时的固定分块
+  Ok(PcBase::new_with_conf(
+    &values,
+    PcConf::default().with_mode(BlockMode::Fixed),
+  ))
+}
+
+/// Decode every value of header-less bytes, checking each read. Block metas
+/// hold the global line, read as `base = slope_fp` and `slope = intercept_fp`;
+/// exceptions are codes in rank order, flagged by one bitmap bit per element.
+/// 解码无文件头字节的全部值，每次读取都做检查。块元数据存全局直线，按
+/// `base = slope_fp`、`slope = intercept_fp` 读取；异常值是按秩排列的码，
+/// 由每元素一位的位图标记。
+fn v0_values<const B: usize>(
+  len: usize,
+  block_meta: &[BlockMeta],
+  residuals: &[u64],
+  exceptions: &[u64],
+  bitmap: &[u64],
+) -> Result<Vec<u64>> {
+  let bad = |msg: &str| Err(PgmError::InvalidData(msg.into()));
+  check_layout::<B>(BlockMode::Fixed, len, block_meta.len(), &[], &[], 0)?;
+  if (bitmap.len() as u128) * 64 < len as u128 {
+    return bad("Exception bitmap shorter than len");
+  }
+
+  let residual_bits = residuals.len() as u64 * 64;
+  let mut values = Vec::with_capacity(len);
+  for (b_idx, meta) in block_meta.iter().enumerate() {
+    let (start, end) = (b_idx * B, ((b_idx + 1) * B).min(len));
+    let (bit, mut ex) = (meta.bit_offset as u64, meta.exception_offset as usize);
+    let w = meta.bit_width;
+    if w > 64 || bit + (end - start) as u64 * w as u64 > residual_bits {
+      return bad("Residual bits out of range");
+    }
+
+    let flagged = meta.flags & FLAG_HAS_EX != 0;
+    for i in start..end {
+      let code = if flagged && (bitmap[i / 64] >> (i % 64)) & 1 == 1 {
+        let Some(&code) = exceptions.get(ex) else {
+          return bad("Exception index out of range");
+        };
+        ex += 1;
+        code
+      } else if w == 0 {
+        0
+      } else {
+        read_bits(residuals, bit as usize + (i - start) * w as usize, w)
+      };
+      let res = ((code >> 1) as i64 ^ -((code & 1) as i64)) as u64;
+      let pred = (((i as u128 * meta.base as u128) as i128 + meta.slope as i128) >> 32) as u64;
+      values.push(pred.wrapping_add(res));
+    }
+  }
+  Ok(values)
 }
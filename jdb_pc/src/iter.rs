@@ -1,179 +1,225 @@
 //! Iterator implementations for Pc.
-// Pc 迭代器实现。
+//! Pc 迭代器实现。
+//!
+//! Both directions decode one whole block at a time into a buffer; the
+//! front and the back keep a buffer each, and share one when they meet.
+//! 两个方向都一次把整块解码进缓冲区；前端与后端各有一个缓冲区，
+//! 相遇时共用其一。
+
+use super::{PcViewBase, types::PcInt};
+
+/// One decoded block.
+/// 一个已解码的块。
+#[derive(Clone, Debug)]
+struct Buf<T> {
+  b_idx: usize,
+  start: usize,
+  vals: Vec<T>,
+  codes: Vec<u64>,
+}
 
-use jdb_pgm_lib::bits::read_bits;
+impl<T: PcInt> Buf<T> {
+  fn new() -> Self {
+    Self {
+      b_idx: 0,
+      start: 0,
+      vals: Vec::new(),
+      codes: Vec::new(),
+    }
+  }
 
-use super::{PcBase, types::FLAG_HAS_EX};
+  /// Decoded value at `index`, if the buffer holds it.
+  /// 缓冲区含 `index` 时返回其解码值。
+  #[inline(always)]
+  fn get(&self, index: usize) -> Option<T> {
+    self.vals.get(index.wrapping_sub(self.start)).copied()
+  }
 
-/// Forward iterator for Pc.
-/// Pc 正向迭代器。
-pub struct PcIterBase<'a, const B: usize> {
-  pub(crate) pc: &'a PcBase<B>,
-  pub(crate) current_idx: usize,
-  pub(crate) end_idx: usize,
-  pub(crate) current_block_idx: isize,
-  pub(crate) buffer: Vec<u64>,
-  pub(crate) buffer_pos: u16,
-  pub(crate) buffer_len: u16,
+  /// Load block `b_idx`.
+  /// 载入块 `b_idx`。
+  #[inline(never)]
+  fn load<const B: usize>(&mut self, pc: &PcViewBase<'_, B, T>, b_idx: usize) {
+    let (start, end) = pc.block_span(b_idx);
+    self.b_idx = b_idx;
+    self.start = start;
+    self.vals.resize(end - start, T::default());
+    pc.decode_span(b_idx, start, end, &mut self.codes, &mut self.vals);
+  }
 }
 
-impl<const B: usize> Iterator for PcIterBase<'_, B> {
-  type Item = u64;
+/// Double-ended, block-buffered cursor over `front..back`.
+/// 覆盖 `front..back` 的双端分块缓冲游标。
+#[derive(Clone, Debug)]
+pub(crate) struct Cursor<'a, const B: usize, T: PcInt> {
+  pc: PcViewBase<'a, B, T>,
+  front: usize,
+  back: usize,
+  front_buf: Buf<T>,
+  back_buf: Buf<T>,
+}
 
-  #[inline]
-  fn next(&mut self) -> Option<Self::Item> {
-    if self.current_idx >= self.end_idx {
-      return None;
-    }
-    if self.buffer_pos < self.buffer_len {
-      let val = unsafe { *self.buffer.get_unchecked(self.buffer_pos as usize) };
-      self.buffer_pos += 1;
-      self.current_idx += 1;
-      return Some(val);
+impl<'a, const B: usize, T: PcInt> Cursor<'a, B, T> {
+  pub(crate) fn new(pc: PcViewBase<'a, B, T>, front: usize, back: usize) -> Self {
+    Self {
+      pc,
+      front,
+      back,
+      front_buf: Buf::new(),
+      back_buf: Buf::new(),
     }
-    self.refill_buffer();
-    if self.buffer_pos < self.buffer_len {
-      let val = unsafe { *self.buffer.get_unchecked(self.buffer_pos as usize) };
-      self.buffer_pos += 1;
-      self.current_idx += 1;
-      Some(val)
+  }
+
+  #[inline(always)]
+  fn len(&self) -> usize {
+    self.back - self.front
+  }
+
+  /// Value at `index`, from either buffer or by loading its block into
+  /// `front_buf` (`forward`) or `back_buf`. Sequential moves step to the
+  /// neighbouring block without a lookup.
+  /// `index` 处的值，取自任一缓冲区，或将其所在块载入 `front_buf`
+  /// （`forward`）或 `back_buf`。顺序移动直接步进到相邻块，无需查找。
+  #[inline(always)]
+  fn at(&mut self, index: usize, forward: bool) -> T {
+    let (own, other) = if forward {
+      (&mut self.front_buf, &self.back_buf)
     } else {
-      None
+      (&mut self.back_buf, &self.front_buf)
+    };
+    if let Some(v) = own.get(index) {
+      return v;
     }
+    if let Some(v) = other.get(index) {
+      return v;
+    }
+    let b_idx = if own.vals.is_empty() {
+      self.pc.locate(index).0
+    } else if forward && index == own.start + own.vals.len() {
+      own.b_idx + 1
+    } else if !forward && index + 1 == own.start {
+      own.b_idx - 1
+    } else {
+      self.pc.locate(index).0
+    };
+    own.load(&self.pc, b_idx);
+    // SAFETY: the block just loaded holds index
+    unsafe { own.get(index).unwrap_unchecked() }
   }
-}
 
-impl<const B: usize> PcIterBase<'_, B> {
-  /// Refill buffer with next block's decoded values.
-  /// 用下一个块的解码值填充缓冲区。
-  #[inline(never)]
-  fn refill_buffer(&mut self) {
-    let n = self.pc.len;
-    self.current_block_idx += 1;
-    let b_idx = self.current_block_idx as usize;
-    if b_idx >= self.pc.block_meta.len() {
-      self.buffer_len = 0;
-      return;
+  #[inline]
+  fn next(&mut self) -> Option<T> {
+    if self.front >= self.back {
+      return None;
     }
+    let v = self.at(self.front, true);
+    self.front += 1;
+    Some(v)
+  }
 
-    let meta = unsafe { self.pc.block_meta.get_unchecked(b_idx) };
-    let start = b_idx * B;
-    let count = (n - start).min(B);
-    let w = meta.bit_width as usize;
-    let bit_off = meta.bit_offset as usize;
-    let ex_offset = meta.exception_offset as usize;
-
-    let has_ex = (meta.flags & FLAG_HAS_EX) != 0;
-
-    let res_ptr = self.pc.residuals.as_ptr() as *const u8;
-
-    // Block local model
-    let slope_fp = meta.slope_fp;
-    let intercept_fp = meta.intercept_fp;
-
-    // Fast path: No exceptions
-    if !has_ex {
-      let mut cur_fp = (start as u128 * slope_fp as u128) as i128 + intercept_fp as i128;
-      let slope = slope_fp as i128;
-
-      let mask = if w < 64 { (1u64 << w) - 1 } else { !0u64 };
-      let mut global_bit_pos = bit_off;
-      let mut i = 0;
-      while i < count {
-        let code = if w == 0 {
-          0
-        } else if w <= 56 {
-          unsafe {
-            let ptr = res_ptr.add(global_bit_pos >> 3);
-            let shift = (global_bit_pos & 7) as u32;
-            let val = (ptr.cast::<u64>().read_unaligned() >> shift) & mask;
-            global_bit_pos += w;
-            val
-          }
-        } else {
-          let val = read_bits(&self.pc.residuals, global_bit_pos, w as u8);
-          global_bit_pos += w;
-          val
-        };
-        let res = (code >> 1) as i64 ^ -((code & 1) as i64);
-        unsafe {
-          *self.buffer.get_unchecked_mut(i) = ((cur_fp >> 32) as i64).wrapping_add(res) as u64;
-        }
-        cur_fp += slope;
-        i += 1;
-      }
-    } else {
-      // Slow path: Exceptions
-      let mut i = 0;
-      let mut ex_idx = ex_offset;
-      let mut global_bit_pos = bit_off;
-      let mut cur_fp = (start as u128 * slope_fp as u128) as i128 + intercept_fp as i128;
-      let slope = slope_fp as i128;
-
-      let mut current_bm_idx = usize::MAX;
-      let mut current_bm_word = 0;
-
-      while i < count {
-        let g_idx = start + i;
-        let bm_idx = g_idx / 64;
-        let bm_bit = g_idx % 64;
-
-        if bm_idx != current_bm_idx {
-          current_bm_idx = bm_idx;
-          current_bm_word = unsafe { *self.pc.bitmap.get_unchecked(bm_idx) };
-        }
-
-        let is_ex = (current_bm_word >> bm_bit) & 1 == 1;
-
-        let code = if is_ex {
-          let v = unsafe { *self.pc.exceptions.get_unchecked(ex_idx) };
-          ex_idx += 1;
-          if w > 0 {
-            global_bit_pos += w;
-          }
-          v
-        } else {
-          if w == 0 {
-            0
-          } else {
-            let val = read_bits(&self.pc.residuals, global_bit_pos, w as u8);
-            global_bit_pos += w;
-            val
-          }
-        };
-
-        let res = (code >> 1) as i64 ^ -((code & 1) as i64);
-        unsafe {
-          *self.buffer.get_unchecked_mut(i) = ((cur_fp >> 32) as i64).wrapping_add(res) as u64;
-        }
-        cur_fp += slope;
-        i += 1;
-      }
+  #[inline]
+  fn next_back(&mut self) -> Option<T> {
+    if self.front >= self.back {
+      return None;
     }
+    self.back -= 1;
+    Some(self.at(self.back, false))
+  }
+}
+
+/// Forward iterator for Pc.
+/// Pc 正向迭代器。
+#[derive(Clone, Debug)]
+pub struct PcIterBase<'a, const B: usize, T: PcInt = u64> {
+  pub(crate) cur: Cursor<'a, B, T>,
+}
+
+impl<const B: usize, T: PcInt> PcIterBase<'_, B, T> {
+  /// Move forward so the next value is the one at `index`; positions
+  /// already passed or past the end of the range are clamped. The decoded
+  /// block is kept when `index` falls inside it.
+  /// 向前移动，使下一个值为 `index` 处的值；已越过或超出区间末尾的位置会
+  /// 被截断。`index` 落在当前解码块内时保留该块。
+  pub fn skip_to(&mut self, index: usize) {
+    self.cur.front = index.clamp(self.cur.front, self.cur.back);
+  }
+}
+
+impl<const B: usize, T: PcInt> Iterator for PcIterBase<'_, B, T> {
+  type Item = T;
 
-    self.buffer_pos = (self.current_idx % B) as u16;
-    self.buffer_len = count as u16;
+  #[inline]
+  fn next(&mut self) -> Option<Self::Item> {
+    self.cur.next()
+  }
+
+  #[inline]
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (self.cur.len(), Some(self.cur.len()))
+  }
+
+  #[inline]
+  fn nth(&mut self, n: usize) -> Option<Self::Item> {
+    self.skip_to(self.cur.front.saturating_add(n));
+    self.cur.next()
+  }
+}
+
+impl<const B: usize, T: PcInt> DoubleEndedIterator for PcIterBase<'_, B, T> {
+  #[inline]
+  fn next_back(&mut self) -> Option<Self::Item> {
+    self.cur.next_back()
   }
 }
 
+impl<const B: usize, T: PcInt> ExactSizeIterator for PcIterBase<'_, B, T> {}
+
+impl<const B: usize, T: PcInt> std::iter::FusedIterator for PcIterBase<'_, B, T> {}
+
 /// Reverse iterator for Pc
 /// Pc 逆向迭代器
-pub struct PcRevIterBase<'a, const B: usize> {
-  pub(crate) pc: &'a PcBase<B>,
-  pub(crate) current_idx: isize,
-  pub(crate) start_idx: isize,
+#[derive(Clone, Debug)]
+pub struct PcRevIterBase<'a, const B: usize, T: PcInt = u64> {
+  pub(crate) cur: Cursor<'a, B, T>,
 }
 
-impl<const B: usize> Iterator for PcRevIterBase<'_, B> {
-  type Item = u64;
+impl<const B: usize, T: PcInt> PcRevIterBase<'_, B, T> {
+  /// Move backward so the next value is the one at `index`; positions
+  /// already passed or before the start of the range are clamped. The
+  /// decoded block is kept when `index` falls inside it.
+  /// 向后移动，使下一个值为 `index` 处的值；已越过或早于区间起点的位置会
+  /// 被截断。`index` 落在当前解码块内时保留该块。
+  pub fn skip_to(&mut self, index: usize) {
+    self.cur.back = index.saturating_add(1).clamp(self.cur.front, self.cur.back);
+  }
+}
+
+impl<const B: usize, T: PcInt> Iterator for PcRevIterBase<'_, B, T> {
+  type Item = T;
 
   #[inline]
   fn next(&mut self) -> Option<Self::Item> {
-    if self.current_idx < self.start_idx {
-      return None;
-    }
-    let idx = self.current_idx as usize;
-    self.current_idx -= 1;
-    unsafe { Some(self.pc.get_unchecked(idx)) }
+    self.cur.next_back()
+  }
+
+  #[inline]
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (self.cur.len(), Some(self.cur.len()))
+  }
+
+  #[inline]
+  fn nth(&mut self, n: usize) -> Option<Self::Item> {
+    self.cur.back = self.cur.back.saturating_sub(n).max(self.cur.front);
+    self.cur.next_back()
+  }
+}
+
+impl<const B: usize, T: PcInt> DoubleEndedIterator for PcRevIterBase<'_, B, T> {
+  #[inline]
+  fn next_back(&mut self) -> Option<Self::Item> {
+    self.cur.next()
   }
 }
+
+impl<const B: usize, T: PcInt> ExactSizeIterator for PcRevIterBase<'_, B, T> {}
+
+impl<const B: usize, T: PcInt> std::iter::FusedIterator for PcRevIterBase<'_, B, T> {}
@@ -1,12 +1,19 @@
-use jdb_pgm_lib::bits::{BitWriter, read_bits};
-
+pub mod builder;
+mod cone;
+mod enc;
 pub mod io;
 pub mod iter;
+mod search;
 pub mod types;
+mod update;
+pub mod view;
+
+use std::marker::PhantomData;
 
+pub use self::{builder::PcBuilderBase, view::PcViewBase};
 use self::{
   iter::{PcIterBase, PcRevIterBase},
-  types::{BLOCK_LEN, BlockMeta, FLAG_HAS_EX, PcConf},
+  types::{BLOCK_LEN, BlockMeta, BlockMode, ExPenalty, FLAG_HAS_EX, PcConf, PcInt},
 };
 
 /// Type alias using default BLOCK_LEN from build.rs.
@@ -15,20 +22,53 @@ pub type Pc = PcBase<{ BLOCK_LEN }>;
 // Iterators disabled temporarily during refactor or need update
 pub type PcIter<'a> = PcIterBase<'a, { BLOCK_LEN }>;
 pub type PcRevIter<'a> = PcRevIterBase<'a, { BLOCK_LEN }>;
+pub type PcView<'a> = PcViewBase<'a, { BLOCK_LEN }>;
+/// Default BLOCK_LEN over another element type.
+/// 默认 BLOCK_LEN、其他元素类型。
+pub type PcOf<T> = PcBase<{ BLOCK_LEN }, T>;
+pub type PcViewOf<'a, T> = PcViewBase<'a, { BLOCK_LEN }, T>;
+pub type PcBuilder = PcBuilderBase<{ BLOCK_LEN }>;
+pub type PcBuilderOf<T> = PcBuilderBase<{ BLOCK_LEN }, T>;
 
 /// Compact Learning Index with Blocked Bit-Packing (Block-Local Prediction).
 /// 紧凑的学习型索引，使用分块位压缩优化空间（块级本地预测）。
 #[derive(Clone, Debug)]
-pub struct PcBase<const B: usize> {
+pub struct PcBase<const B: usize, T: PcInt = u64> {
   // segments removed
   pub block_meta: Vec<BlockMeta>,
   pub residuals: Vec<u64>,
+  /// Per-block exception sections: in-block positions, then codes at the
+  /// block's `ex_width` (see `BlockMeta::ex_bits`)
+  /// 各块的异常段：块内位置，其后是按该块 `ex_width` 存放的码
+  /// （见 `BlockMeta::ex_bits`）
   pub exceptions: Vec<u64>,
-  pub bitmap: Vec<u64>,
+  /// Block segmentation mode
+  /// 分块模式
+  pub mode: BlockMode,
+  /// Build epsilon, reused whenever blocks are cut or re-encoded again
+  /// 构建时的 epsilon，再次分块或重新编码时沿用
+  pub epsilon: usize,
+  /// Build exception penalty, reused like `epsilon`
+  /// 构建时的离群值惩罚系数，与 `epsilon` 一样沿用
+  pub ex_penalty: ExPenalty,
+  /// Epsilon mode: first index of each block, then `len` as sentinel
+  /// Epsilon 模式：各块首索引，末尾以 `len` 作哨兵
+  pub starts: Vec<u64>,
+  /// Epsilon mode: block holding index `k << dir_shift`, for O(1) lookup
+  /// Epsilon 模式：包含索引 `k << dir_shift` 的块，用于 O(1) 定位
+  pub dir: Vec<u32>,
+  pub dir_shift: u8,
+  /// Large mode: absolute (bit, exception) offset pair per superblock of
+  /// `1 << super_shift(B)` blocks; block metas hold offsets relative to it.
+  /// Empty when every offset fits in u32.
+  /// 大模式：每个含 `1 << super_shift(B)` 块的超块一对绝对（位、异常）
+  /// 偏移；块元数据存相对它的偏移。所有偏移都能放进 u32 时为空。
+  pub anchors: Vec<u64>,
   pub len: usize,
+  pub(crate) elem: PhantomData<T>,
 }
 
-impl<const B: usize> PcBase<B> {
+impl<const B: usize, T: PcInt> PcBase<B, T> {
   /// Serialize to bytes.
   /// 序列化为字节流。
   pub fn dump(&self) -> Vec<u8> {
@@ -42,248 +82,83 @@ impl<const B: usize> PcBase<B> {
   }
 
   /// Build with block-local prediction.
-  pub fn new(data: &[u64], epsilon: usize) -> Self {
+  pub fn new(data: &[T], epsilon: usize) -> Self {
     Self::new_with_conf(data, PcConf::with_epsilon(epsilon))
   }
 
   /// Build with custom configuration.
-  pub fn new_with_conf(data: &[u64], conf: PcConf) -> Self {
-    // epsilon is ignored for segmentation, but could be used implicitly?
-    // In this Block-Local model, we don't satisfy epsilon. We maximize bit-packing.
-    let ex_penalty = conf.ex_penalty.get() as u64;
-    let n = data.len();
-    if n == 0 {
-      return Self::default();
-    }
-
-    let block_count = n.div_ceil(B);
-    let mut block_meta = Vec::with_capacity(block_count);
-    let mut bit_writer = BitWriter::with_capacity(n);
-    let mut bitmap_writer = BitWriter::with_capacity(n / 64 + 1);
-    let mut exceptions = Vec::new();
-    let mut diff_buffer = Vec::with_capacity(B);
-
-    for b_idx in 0..block_count {
-      let start = b_idx * B;
-      let end = (start + B).min(n);
-      let block_data = &data[start..end];
-
-      // 1. Calculate Block Model (First-Last Line)
-      // y = slope * x + intercept
-      let first_val = block_data[0];
-      let last_val = block_data[block_data.len() - 1];
-      let start_x = start as f64;
-      let end_x = (end - 1) as f64;
-
-      let slope = if end > start + 1 {
-        (last_val as f64 - first_val as f64) / (end_x - start_x)
-      } else {
-        0.0
-      };
-      let intercept = first_val as f64 - slope * start_x;
-
-      let slope_fp = (slope * (1u64 << 32) as f64) as u64;
-      let intercept_fp = (intercept * (1u64 << 32) as f64) as i64;
-
-      // 2. Compute Residuals
-      diff_buffer.clear();
-      for (local_idx, &val) in block_data.iter().enumerate() {
-        let global_idx = (start + local_idx) as u64;
-        let pred = ((global_idx as u128 * slope_fp as u128) as i128 + intercept_fp as i128) >> 32;
-        let diff = val.wrapping_sub(pred as u64) as i64;
-        // ZigZag encode
-        diff_buffer.push(((diff as u64) << 1) ^ ((diff >> 63) as u64));
-      }
-
-      // 3. Find optimal bit width
-      let mut best_w = 0;
-      let mut min_cost = u64::MAX;
-      let mut counts = [0usize; 65];
-      for &d in &diff_buffer {
-        counts[if d == 0 {
-          0
-        } else {
-          64 - d.leading_zeros() as usize
-        }] += 1;
-      }
-      let mut num_ex = 0;
-      for w in (0..=64).rev() {
-        num_ex += if w < 64 { counts[w + 1] } else { 0 };
-        let cost = (diff_buffer.len() as u64 * w as u64) + (num_ex as u64 * 64 * ex_penalty);
-        if cost <= min_cost {
-          min_cost = cost;
-          best_w = w as u8;
-        }
-      }
-
-      let bit_width = best_w as usize;
-      let mask = if bit_width >= 64 {
-        !0u64
-      } else {
-        (1u64 << bit_width) - 1
-      };
-      let exception_offset = exceptions.len() as u32;
-      let mut block_has_ex = false;
-
-      let bit_offset = bit_writer.current_bit_offset() as u32;
-      for &code in &diff_buffer {
-        if code <= mask {
-          bitmap_writer.write(0, 1);
-          if bit_width > 0 {
-            bit_writer.write(code, bit_width as u8);
-          }
-        } else {
-          bitmap_writer.write(1, 1);
-          if bit_width > 0 {
-            bit_writer.write(0, bit_width as u8);
-          }
-          exceptions.push(code);
-          block_has_ex = true;
-        }
-      }
-
-      let flags = if block_has_ex { FLAG_HAS_EX } else { 0 };
+  pub fn new_with_conf(data: &[T], conf: PcConf) -> Self {
+    let mut builder = PcBuilderBase::with_capacity(conf, data.len());
+    builder.extend_from_slice(data);
+    builder.finish()
+  }
 
-      block_meta.push(BlockMeta {
-        bit_offset,
-        bit_width: bit_width as u8,
-        flags,
-        exception_offset,
-        slope_fp,
-        intercept_fp,
-      });
+  /// Borrow as a view; every read path lives there.
+  /// 借用为视图；所有读取路径都在视图上。
+  #[inline(always)]
+  pub fn view(&self) -> PcViewBase<'_, B, T> {
+    PcViewBase {
+      block_meta: &self.block_meta,
+      residuals: &self.residuals,
+      exceptions: &self.exceptions,
+      mode: self.mode,
+      epsilon: self.epsilon,
+      ex_penalty: self.ex_penalty,
+      starts: &self.starts,
+      dir: &self.dir,
+      dir_shift: self.dir_shift,
+      anchors: &self.anchors,
+      len: self.len,
+      elem: PhantomData,
     }
+  }
 
-    Self {
-      block_meta,
-      residuals: bit_writer.finish(),
-      exceptions,
-      bitmap: bitmap_writer.finish(),
-      len: n,
-    }
+  /// Number of blocks.
+  /// 块数。
+  #[inline]
+  pub fn block_count(&self) -> usize {
+    self.block_meta.len()
   }
 
   /// Get value at index.
   #[inline(always)]
-  pub fn get(&self, index: usize) -> Option<u64> {
-    if index >= self.len {
-      return None;
-    }
-    unsafe { Some(self.get_unchecked(index)) }
+  pub fn get(&self, index: usize) -> Option<T> {
+    self.view().get(index)
   }
 
   /// Get value at index without bounds check.
   /// # Safety
   /// Caller must ensure index < self.len().
   #[inline(always)]
-  pub unsafe fn get_unchecked(&self, index: usize) -> u64 {
-    let b_idx = index / B;
-    // Step 1: Lookup Meta (1 cache miss)
-    // SAFETY: index checked by caller. b_idx valid.
-    let meta = unsafe { self.block_meta.get_unchecked(b_idx) };
-
-    // Step 2: Predict using Block Model (Inline)
-    let pred = ((index as u128 * meta.slope_fp as u128) as i128 + meta.intercept_fp as i128) >> 32;
-
-    // Step 3: Decode Residual (1 cache miss if no flags, 2 if exception)
-    let w = meta.bit_width as usize;
-    if (meta.flags & FLAG_HAS_EX) == 0 {
-      // Hot path: No exceptions
-      let code = if w == 0 {
-        0
-      } else {
-        read_bits(
-          &self.residuals,
-          meta.bit_offset as usize + (index % B) * w,
-          w as u8,
-        )
-      };
-
-      let res = (code >> 1) as i64 ^ -((code & 1) as i64);
-      (pred as u64).wrapping_add(res as u64)
-    } else {
-      unsafe { self.get_exception_cold(index, b_idx, meta, pred) }
-    }
+  pub unsafe fn get_unchecked(&self, index: usize) -> T {
+    unsafe { self.view().get_unchecked(index) }
   }
 
-  #[cold]
-  unsafe fn get_exception_cold(
-    &self,
-    index: usize,
-    b_idx: usize,
-    meta: &BlockMeta,
-    pred: i128,
-  ) -> u64 {
-    let bm_idx = index / 64;
-    let bm_bit = index % 64;
-
-    // Check if current index is an exception
-    if (unsafe { *self.bitmap.get_unchecked(bm_idx) } >> bm_bit) & 1 == 1 {
-      let start_word = (b_idx * B) / 64;
-      let mut rank = 0;
-      // Count ones in previous words within the block range
-      // Since B is small, this loop runs 0-2 times usually.
-      for i in start_word..bm_idx {
-        rank += unsafe { self.bitmap.get_unchecked(i).count_ones() };
-      }
-      // Count ones in current word up to current bit
-      rank += (unsafe { *self.bitmap.get_unchecked(bm_idx) } & ((1u64 << bm_bit) - 1)).count_ones();
-      unsafe {
-        *self
-          .exceptions
-          .get_unchecked(meta.exception_offset as usize + rank as usize)
-      }
-    } else {
-      // It's a normal value in a block that has SOME exceptions
-      let w = meta.bit_width as usize;
-      let code = if w == 0 {
-        0
-      } else {
-        read_bits(
-          &self.residuals,
-          meta.bit_offset as usize + (index % B) * w,
-          w as u8,
-        )
-      };
-      let res = (code >> 1) as i64 ^ -((code & 1) as i64);
-      (pred as u64).wrapping_add(res as u64)
-    }
+  /// See [`PcViewBase::get_range_into`].
+  /// 见 [`PcViewBase::get_range_into`]。
+  #[inline]
+  pub fn get_range_into(&self, out: &mut [T], range: std::ops::Range<usize>) {
+    self.view().get_range_into(out, range)
   }
 
   /// Create forward iterator starting from index 0.
-  pub fn iter(&self) -> PcIterBase<'_, B> {
-    self.iter_range(0..self.len)
+  pub fn iter(&self) -> PcIterBase<'_, B, T> {
+    self.view().iter()
   }
 
   /// Create forward iterator for range.
-  pub fn iter_range(&self, range: std::ops::Range<usize>) -> PcIterBase<'_, B> {
-    let start = range.start.min(self.len);
-    let end = range.end.min(self.len);
-    PcIterBase {
-      pc: self,
-      current_idx: start,
-      end_idx: end,
-      current_block_idx: (start as isize / B as isize) - 1,
-      buffer: vec![0; B],
-      buffer_pos: 0,
-      buffer_len: 0,
-    }
+  pub fn iter_range(&self, range: std::ops::Range<usize>) -> PcIterBase<'_, B, T> {
+    self.view().iter_range(range)
   }
 
   /// Create reverse iterator starting from the last element.
-  pub fn rev_iter(&self) -> PcRevIterBase<'_, B> {
-    self.rev_iter_range(0..self.len)
+  pub fn rev_iter(&self) -> PcRevIterBase<'_, B, T> {
+    self.view().rev_iter()
   }
 
   /// Create reverse iterator for range, iterating from end-1 down to start.
-  pub fn rev_iter_range(&self, range: std::ops::Range<usize>) -> PcRevIterBase<'_, B> {
-    let start = range.start.min(self.len);
-    let end = range.end.min(self.len);
-    PcRevIterBase {
-      pc: self,
-      current_idx: end as isize - 1,
-      start_idx: start as isize,
-    }
+  pub fn rev_iter_range(&self, range: std::ops::Range<usize>) -> PcRevIterBase<'_, B, T> {
+    self.view().rev_iter_range(range)
   }
 
   pub fn print_stats(&self) {
@@ -299,12 +174,16 @@ impl<const B: usize> PcBase<B> {
       }
     }
 
-    // Count exceptions
-    let total_exceptions = self.exceptions.len();
+    let total_exceptions = self.exception_count();
 
     println!("--- PC Stats (Block-Local) ---");
     println!("Total Keys: {}", self.len);
+    println!("Block Mode: {:?}", self.mode);
     println!("Total Blocks: {}", total_blocks);
+    println!(
+      "Avg Block Len: {:.2}",
+      self.len as f64 / total_blocks.max(1) as f64
+    );
     println!(
       "Exceptions: {} ({:.2}%)",
       total_exceptions,
@@ -333,18 +212,76 @@ impl<const B: usize> PcBase<B> {
       + self.block_meta.len() * std::mem::size_of::<BlockMeta>()
       + self.residuals.len() * 8
       + self.exceptions.len() * 8
-      + self.bitmap.len() * 8
+      + self.starts.len() * 8
+      + self.dir.len() * 4
+      + self.anchors.len() * 8
+  }
+
+  /// Number of values stored as exceptions.
+  /// 以异常值存储的值个数。
+  pub fn exception_count(&self) -> usize {
+    self.block_meta.iter().map(|m| m.ex_count as usize).sum()
+  }
+
+  /// Whether offsets are anchored per superblock (see `anchors`).
+  /// 偏移是否按超块锚定（见 `anchors`）。
+  #[inline]
+  pub fn is_large(&self) -> bool {
+    !self.anchors.is_empty()
+  }
+
+  /// Configuration this Pc was built with; `large` follows the current layout.
+  /// 构建此 Pc 所用的配置；`large` 取决于当前布局。
+  pub fn conf(&self) -> PcConf {
+    let conf = PcConf::new(self.epsilon, self.ex_penalty).with_mode(self.mode);
+    if self.is_large() {
+      conf.with_large()
+    } else {
+      conf
+    }
   }
 }
 
-impl<const B: usize> Default for PcBase<B> {
+impl<const B: usize, T: PcInt> Default for PcBase<B, T> {
   fn default() -> Self {
     Self {
       block_meta: vec![],
       residuals: vec![],
       exceptions: vec![],
-      bitmap: vec![],
+      mode: BlockMode::default(),
+      epsilon: PcConf::default().epsilon,
+      ex_penalty: ExPenalty::default(),
+      starts: vec![],
+      dir: vec![],
+      dir_shift: 0,
+      anchors: vec![],
       len: 0,
+      elem: PhantomData,
+    }
+  }
+}
+
+/// Directory over block starts: slot `k` holds the block containing
+/// `k << shift`, with the bucket no wider than the average block.
+/// 块首索引目录：槽 `k` 存包含 `k << shift` 的块，桶宽不超过平均块长。
+pub(crate) fn build_dir(starts: &[u64]) -> (Vec<u32>, u8) {
+  let (Some(&n), true) = (starts.last(), starts.len() > 1) else {
+    return (vec![], 0);
+  };
+  let n = n as usize;
+  let blocks = starts.len() - 1;
+  let avg = (n / blocks).max(1);
+  let shift = (usize::BITS - 1 - avg.leading_zeros()) as u8;
+
+  let slots = ((n - 1) >> shift) + 1;
+  let mut dir = Vec::with_capacity(slots);
+  let mut b_idx = 0;
+  for k in 0..slots {
+    let index = (k << shift) as u64;
+    while starts[b_idx + 1] <= index {
+      b_idx += 1;
     }
+    dir.push(u32::try_from(b_idx).expect("epsilon directory holds u32 block indices"));
   }
+  (dir, shift)
 }
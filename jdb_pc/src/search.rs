@@ -0,0 +1,88 @@
+//! Value search over non-decreasing sequences
+//! 非递减序列上的值查找
+//!
+//! Blocks are ordered by their first value, which is the block's line anchor
+//! `base`, so one binary search over block metadata picks the block and a
+//! second one runs inside it. Only in-block probes touch the residual stream,
+//! each a single O(1) `get_unchecked`; nothing is decoded in bulk.
+//! 各块按首值有序，首值即块的直线锚点 `base`，因此先在块元数据上二分选出块，
+//! 再在块内二分。只有块内探测访问残差流，每次都是一次 O(1) 的
+//! `get_unchecked`，不做整块解码。
+
+use super::{PcBase, PcViewBase, types::PcInt};
+
+/// First `i` in `lo..hi` with `!pred(i)`, assuming `pred` is true then false
+/// `lo..hi` 中第一个使 `pred(i)` 为假的 `i`，假定 `pred` 先真后假
+#[inline]
+fn partition(mut lo: usize, mut hi: usize, pred: impl Fn(usize) -> bool) -> usize {
+  while lo < hi {
+    let mid = lo + (hi - lo) / 2;
+    if pred(mid) {
+      lo = mid + 1;
+    } else {
+      hi = mid;
+    }
+  }
+  lo
+}
+
+impl<const B: usize, T: PcInt> PcViewBase<'_, B, T> {
+  /// First index whose value is not less than `value` (`len` if none).
+  /// Requires the sequence to be non-decreasing.
+  /// 第一个值不小于 `value` 的索引（不存在则为 `len`）。要求序列非递减。
+  pub fn lower_bound(&self, value: T) -> usize {
+    // SAFETY: block starts and in-block probes are all < len
+    let below = |i: usize| unsafe { self.get_unchecked(i) } < value;
+
+    // First block whose head is >= value; the answer is in the block before
+    // it or is its head
+    // 第一个首值 >= value 的块；答案在其前一块中，或正是其首元素
+    let b_idx = partition(0, self.block_count(), |b| {
+      T::from_bits(self.block_meta[b].base) < value
+    });
+    if b_idx == 0 {
+      return 0;
+    }
+    let (start, end) = self.block_span(b_idx - 1);
+    partition(start + 1, end, below)
+  }
+
+  /// Number of values less than `value`.
+  /// Requires the sequence to be non-decreasing.
+  /// 小于 `value` 的值的个数。要求序列非递减。
+  #[inline]
+  pub fn rank(&self, value: T) -> usize {
+    self.lower_bound(value)
+  }
+
+  /// Whether `value` occurs. Requires the sequence to be non-decreasing.
+  /// `value` 是否存在。要求序列非递减。
+  #[inline]
+  pub fn contains(&self, value: T) -> bool {
+    let pos = self.lower_bound(value);
+    pos < self.len && unsafe { self.get_unchecked(pos) } == value
+  }
+}
+
+impl<const B: usize, T: PcInt> PcBase<B, T> {
+  /// See [`PcViewBase::lower_bound`].
+  /// 见 [`PcViewBase::lower_bound`]。
+  #[inline]
+  pub fn lower_bound(&self, value: T) -> usize {
+    self.view().lower_bound(value)
+  }
+
+  /// See [`PcViewBase::rank`].
+  /// 见 [`PcViewBase::rank`]。
+  #[inline]
+  pub fn rank(&self, value: T) -> usize {
+    self.view().rank(value)
+  }
+
+  /// See [`PcViewBase::contains`].
+  /// 见 [`PcViewBase::contains`]。
+  #[inline]
+  pub fn contains(&self, value: T) -> bool {
+    self.view().contains(value)
+  }
+}
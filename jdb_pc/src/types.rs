@@ -2,6 +2,133 @@
 /// BlockMeta.flags: 块有异常值
 pub const FLAG_HAS_EX: u8 = 0x80;
 
+/// Integer element stored in a Pc.
+/// Residuals are taken modulo `2^BITS`, so exceptions fit the native width.
+/// Pc 中存储的整数元素。残差按 `2^BITS` 取模，异常值因此以原生宽度存储。
+pub trait PcInt: Copy + Default + Ord + core::fmt::Debug + Send + Sync + 'static {
+  /// Serialized element tag: byte width, 0x80 set when signed
+  /// 序列化元素标记：字节宽度，有符号时置 0x80
+  const TAG: u8;
+  /// Bit width
+  /// 位宽
+  const BITS: u32;
+
+  /// Bit pattern, zero-extended
+  /// 零扩展的位模式
+  fn to_bits(self) -> u64;
+
+  /// Keep the low `BITS` bits
+  /// 取低 `BITS` 位
+  fn from_bits(bits: u64) -> Self;
+
+  /// Numeric value
+  /// 数值
+  fn to_i128(self) -> i128;
+
+  /// Sign-extend a `BITS`-wide difference to i64
+  /// 将 `BITS` 位宽的差值符号扩展为 i64
+  #[inline(always)]
+  fn narrow(diff: u64) -> i64 {
+    let shift = 64 - Self::BITS;
+    ((diff << shift) as i64) >> shift
+  }
+}
+
+macro_rules! impl_pc_int {
+  ($($t:ty => $u:ty, $signed:expr);* $(;)?) => {
+    $(
+      impl PcInt for $t {
+        const TAG: u8 = size_of::<$t>() as u8 | if $signed { 0x80 } else { 0 };
+        const BITS: u32 = <$t>::BITS;
+
+        #[inline(always)]
+        fn to_bits(self) -> u64 {
+          self as $u as u64
+        }
+
+        #[inline(always)]
+        fn from_bits(bits: u64) -> Self {
+          bits as $t
+        }
+
+        #[inline(always)]
+        fn to_i128(self) -> i128 {
+          self as i128
+        }
+      }
+    )*
+  };
+}
+
+impl_pc_int! {
+  u8 => u8, false;
+  u16 => u16, false;
+  u32 => u32, false;
+  u64 => u64, false;
+  i8 => u8, true;
+  i16 => u16, true;
+  i32 => u32, true;
+  i64 => u64, true;
+}
+
+/// Epsilon-mode blocks hold at most `B * MAX_BLOCK_MUL` elements
+/// Epsilon 模式下每块最多 `B * MAX_BLOCK_MUL` 个元素
+pub const MAX_BLOCK_MUL: usize = 8;
+
+/// Epsilon mode falls back to a fixed `B`-element block when the cone covers
+/// fewer than `B / MIN_CONE_DIV` values: noisy data then pays residual width
+/// and exceptions instead of one 32-byte block meta per few values
+/// 当收缩锥覆盖的值少于 `B / MIN_CONE_DIV` 个时，epsilon 模式退回固定的
+/// `B` 元素块：噪声数据由残差位宽与异常值承担，而非每几个值一个 32 字节块元数据
+pub const MIN_CONE_DIV: usize = 4;
+
+/// `compact` re-encodes blocks where more than `1 / COMPACT_EX_DIV` of the
+/// values are exceptions
+/// `compact` 重新编码异常值占比超过 `1 / COMPACT_EX_DIV` 的块
+pub const COMPACT_EX_DIV: usize = 8;
+
+/// Large mode: blocks per superblock is `1 << super_shift(B)`, small enough
+/// that offsets relative to the superblock's anchor always fit in u32. A
+/// block of `len <= B * MAX_BLOCK_MUL` values spends at most `len * 64`
+/// residual bits and, with every value an exception (as `set` may leave it),
+/// `len * (pos_width(len) + 64)` exception bits.
+/// 大模式：每超块含 `1 << super_shift(B)` 个块，足够小，使相对超块锚点的
+/// 偏移总能放进 u32。含 `len <= B * MAX_BLOCK_MUL` 个值的块至多占 `len * 64`
+/// 位残差；若每个值都是异常值（`set` 可能造成），异常段至多
+/// `len * (pos_width(len) + 64)` 位。
+pub const fn super_shift(block_len: usize) -> u32 {
+  let len = block_len * MAX_BLOCK_MUL;
+  let max_bits = (len * (pos_width(len) as usize + 64)).next_power_of_two();
+  32u32.saturating_sub(max_bits.trailing_zeros())
+}
+
+/// How the sequence is cut into blocks
+/// 序列的分块方式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(u8)]
+pub enum BlockMode {
+  /// Fixed `B`-element blocks, one first-to-last line each
+  /// 固定 `B` 元素分块，每块一条首尾连线
+  Fixed = 0,
+  /// Variable-length blocks that end where the line leaves ±epsilon
+  /// 变长分块，直线超出 ±epsilon 处结束
+  #[default]
+  Epsilon = 1,
+}
+
+impl BlockMode {
+  /// Decode from the serialized tag
+  /// 从序列化标记解码
+  #[inline]
+  pub fn from_u8(tag: u8) -> Option<Self> {
+    match tag {
+      0 => Some(Self::Fixed),
+      1 => Some(Self::Epsilon),
+      _ => None,
+    }
+  }
+}
+
 #[derive(Clone, Copy, Debug, Default)]
 #[repr(C)]
 pub struct BlockMeta {
@@ -11,18 +138,64 @@ pub struct BlockMeta {
   /// Start index in the exceptions array for this block
   /// 该块在异常值数组中的起始索引
   pub exception_offset: u32,
-  /// Block slope (fixed point)
-  /// 块斜率（定点数）
-  pub slope_fp: u64,
-  /// Block intercept (fixed point)
-  /// 块截距（定点数）
-  pub intercept_fp: i64,
+  /// Bit pattern of the first element, the line's anchor
+  /// 首元素的位模式，即直线锚点
+  pub base: u64,
+  /// Signed slope per element, fixed point with `shift` fractional bits
+  /// 每元素的有符号斜率，定点数，含 `shift` 位小数
+  pub slope: i64,
   /// Bit width for each element in the block
   /// 块中每个元素的位宽
   pub bit_width: u8,
   /// Flags (e.g. has exceptions)
   /// 标志位（如是否有异常值）
   pub flags: u8,
+  /// Fractional bits of `slope` (0..=32)
+  /// `slope` 的小数位数（0..=32）
+  pub shift: u8,
+  /// Bit width of this block's exception codes
+  /// 该块异常码的位宽
+  pub ex_width: u8,
+  /// Number of exceptions in this block
+  /// 该块的异常值个数
+  pub ex_count: u16,
+}
+
+impl BlockMeta {
+  /// Predicted bit pattern `k` elements after the block start.
+  /// Arithmetic wraps mod 2^64, which matches the mod-2^BITS residuals.
+  /// 块首之后第 `k` 个元素的预测位模式。
+  /// 运算按 2^64 回绕，与按 2^BITS 取模的残差一致。
+  #[inline(always)]
+  pub fn predict(&self, k: usize) -> u64 {
+    self
+      .base
+      .wrapping_add(((k as i64).wrapping_mul(self.slope) >> self.shift) as u64)
+  }
+
+  /// Bits of the exception section of a block holding `len` values:
+  /// `ex_count` positions, then `ex_count` codes.
+  /// 含 `len` 个值的块的异常段位数：`ex_count` 个位置，其后 `ex_count` 个码。
+  #[inline(always)]
+  pub fn ex_bits(&self, len: usize) -> usize {
+    self.ex_count as usize * (pos_width(len) + self.ex_width) as usize
+  }
+}
+
+/// Bits per in-block exception position in a block of `len` values.
+/// 含 `len` 个值的块中每个块内异常位置的位数。
+#[inline(always)]
+pub const fn pos_width(len: usize) -> u8 {
+  (usize::BITS - len.saturating_sub(1).leading_zeros()) as u8
+}
+
+/// Most exceptions a block of residual width `w` can hold: an exception's
+/// residual slot stores its rank, and the count has to fit `ex_count`.
+/// 残差位宽为 `w` 的块最多能容纳的异常值个数：异常值的残差槽存其秩，
+/// 且个数须能放进 `ex_count`。
+#[inline(always)]
+pub(crate) const fn max_ex(w: u8) -> usize {
+  if w >= 16 { u16::MAX as usize } else { 1 << w }
 }
 
 /// Exception penalty multiplier for PFOR cost calculation.
@@ -99,26 +272,57 @@ pub struct PcConf {
   /// Exception penalty multiplier.
   /// 离群值惩罚系数。
   pub ex_penalty: ExPenalty,
+
+  /// Block segmentation mode.
+  /// 分块模式。
+  pub mode: BlockMode,
+
+  /// Force 64-bit offsets even when u32 would do; otherwise the build
+  /// switches on its own once offsets pass u32.
+  /// 即使 u32 够用也强制使用 64 位偏移；否则偏移超过 u32 时构建会自动切换。
+  pub large: bool,
 }
 
 impl PcConf {
-  /// Create a new configuration.
-  /// 创建新配置。
+  /// Create a new configuration; blocks are cut by `epsilon`.
+  /// 创建新配置；按 `epsilon` 分块。
   #[inline]
   pub fn new(epsilon: usize, ex_penalty: ExPenalty) -> Self {
     Self {
       epsilon,
       ex_penalty,
+      mode: BlockMode::Epsilon,
+      large: false,
     }
   }
 
-  /// Create configuration with default ex_penalty (1).
-  /// 使用默认离群值惩罚（1）创建配置。
+  /// Create configuration with default ex_penalty (1); blocks are cut by
+  /// `epsilon`.
+  /// 使用默认离群值惩罚（1）创建配置；按 `epsilon` 分块。
   #[inline]
   pub fn with_epsilon(epsilon: usize) -> Self {
     Self {
       epsilon,
       ex_penalty: ExPenalty::default(),
+      mode: BlockMode::Epsilon,
+      large: false,
+    }
+  }
+
+  /// Same configuration with another block mode.
+  /// 更换分块模式的同一配置。
+  #[inline]
+  pub fn with_mode(self, mode: BlockMode) -> Self {
+    Self { mode, ..self }
+  }
+
+  /// Same configuration with large mode forced on.
+  /// 强制开启大模式的同一配置。
+  #[inline]
+  pub fn with_large(self) -> Self {
+    Self {
+      large: true,
+      ..self
     }
   }
 }
@@ -128,6 +332,8 @@ impl Default for PcConf {
     Self {
       epsilon: DEFAULT_EPSILON,
       ex_penalty: ExPenalty::default(),
+      mode: BlockMode::Epsilon,
+      large: false,
     }
   }
 }
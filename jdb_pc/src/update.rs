@@ -0,0 +1,271 @@
+//! In-place point updates and compaction
+//! 原地单点更新与压缩整理
+//!
+//! `set` keeps every block boundary and bit width. A residual or exception
+//! code that still fits is overwritten in place, in O(1). Anything else
+//! becomes an exception: its block's section grows, so the exception stream
+//! is copied once with the later sections moved along, in O(exception
+//! stream). Setting a block head to another value, or adding an exception a
+//! block's width cannot rank, re-encodes that block and copies every other
+//! block's residuals and exceptions, in O(residual + exception streams).
+//! Residual bit offsets stay put on all but that last path. `compact` later
+//! re-encodes the blocks that collected too many exceptions.
+//! `set` 保持所有块边界与位宽不变。仍能放下的残差或异常码原地覆写，O(1)。
+//! 其余的成为异常值：所在块的异常段变长，异常流整体复制一次并后移之后的
+//! 各段，O(异常流)。把块首改为其他值，或块位宽无法再给异常值编秩时，重新
+//! 编码该块并复制其余各块的残差与异常值，O(残差流 + 异常流)。除最后这条
+//! 路径外，残差位偏移均不变。`compact` 随后重新编码积累了过多异常值的块。
+
+use jdb_pgm_lib::bits::{BitWriter, read_bits, unpack, write_bits};
+
+use super::{
+  PcBase,
+  enc::{Enc, anchor, fits, line_slope},
+  types::{BlockMeta, COMPACT_EX_DIV, FLAG_HAS_EX, PcInt, max_ex, pos_width, super_shift},
+};
+
+impl<const B: usize, T: PcInt> PcBase<B, T> {
+  /// Replace the value at `index`.
+  /// 替换 `index` 处的值。
+  ///
+  /// # Panics
+  /// If `index >= len`.
+  pub fn set(&mut self, index: usize, value: T) {
+    assert!(
+      index < self.len,
+      "index {index} out of range for Pc of length {}",
+      self.len
+    );
+    let view = self.view();
+    let (b_idx, start) = view.locate(index);
+    let len = view.block_span(b_idx).1 - start;
+    let meta = self.block_meta[b_idx];
+    let (bit_off, ex_off) = view.offsets(b_idx, &meta);
+
+    let k = index - start;
+    // Block heads stay equal to their anchor, which `lower_bound` relies on
+    // 块首始终等于其锚点，`lower_bound` 依赖于此
+    if k == 0 && value.to_bits() != meta.base {
+      self.rebuild(|b| b == b_idx, Some((index, value)));
+      return;
+    }
+    let diff = T::narrow(value.to_bits().wrapping_sub(meta.predict(k)));
+    let code = ((diff as u64) << 1) ^ ((diff >> 63) as u64);
+    let w = meta.bit_width;
+    let slot_at = bit_off + k * w as usize;
+    let slot = if w == 0 {
+      0
+    } else {
+      read_bits(&self.residuals, slot_at, w)
+    };
+    let rank = if meta.flags & FLAG_HAS_EX == 0 {
+      None
+    } else {
+      view.ex_rank(&meta, ex_off, len, k, slot)
+    };
+
+    let n = meta.ex_count as usize;
+    let pw = pos_width(len);
+    match rank {
+      Some(r) if fits(code, meta.ex_width) => {
+        if meta.ex_width > 0 {
+          let at = ex_off + n * pw as usize + r * meta.ex_width as usize;
+          write_bits(&mut self.exceptions, at, meta.ex_width, code);
+        }
+      }
+      None if fits(code, w) => {
+        if w > 0 {
+          write_bits(&mut self.residuals, slot_at, w, code);
+        }
+      }
+      _ => {
+        let (mut pos, mut codes) = (vec![0; n], vec![0; n]);
+        if n > 0 {
+          unpack(&self.exceptions, ex_off, pw, &mut pos);
+          unpack(
+            &self.exceptions,
+            ex_off + n * pw as usize,
+            meta.ex_width,
+            &mut codes,
+          );
+        }
+        match rank {
+          Some(r) => codes[r] = code,
+          // Slots cannot rank another exception: re-encode the block
+          // 槽位无法再给异常值编秩：重新编码该块
+          None if n >= max_ex(w) => {
+            self.rebuild(|b| b == b_idx, Some((index, value)));
+            return;
+          }
+          None => {
+            let r = pos.partition_point(|&p| p < k as u64);
+            pos.insert(r, k as u64);
+            codes.insert(r, code);
+          }
+        }
+        self.put_exceptions(b_idx, len, &pos, &codes);
+      }
+    }
+  }
+
+  /// Rewrite the exception section of block `b_idx` (`len` values) to hold
+  /// `pos` and `codes`, store each exception's rank in its residual slot and
+  /// move later sections along. The code width never shrinks, so neither
+  /// does the section.
+  /// 将块 `b_idx`（`len` 个值）的异常段改写为 `pos` 与 `codes`，在各异常值
+  /// 的残差槽中存其秩，并后移之后的各段。码宽只增不减，段长亦然。
+  fn put_exceptions(&mut self, b_idx: usize, len: usize, pos: &[u64], codes: &[u64]) {
+    let view = self.view();
+    let meta = self.block_meta[b_idx];
+    let (bit_off, ex_off) = view.offsets(b_idx, &meta);
+    let old_bits = meta.ex_bits(len);
+    let used = match self.block_meta.len().checked_sub(1) {
+      Some(last) => {
+        let (start, end) = view.block_span(last);
+        view.offsets(last, &self.block_meta[last]).1 + self.block_meta[last].ex_bits(end - start)
+      }
+      None => 0,
+    };
+
+    let need = codes.iter().map(|&c| 64 - c.leading_zeros()).max();
+    let ew = meta.ex_width.max(need.unwrap_or(0) as u8);
+    let pw = pos_width(len);
+    let mut w = BitWriter::with_capacity(used + pos.len() * (pw + ew) as usize);
+    w.write_run(&self.exceptions, 0, ex_off);
+    pos.iter().for_each(|&p| w.write(p, pw));
+    codes.iter().for_each(|&c| w.write(c, ew));
+    w.write_run(
+      &self.exceptions,
+      ex_off + old_bits,
+      used - ex_off - old_bits,
+    );
+    self.exceptions = w.finish();
+
+    let m = &mut self.block_meta[b_idx];
+    m.ex_count = pos.len() as u16;
+    m.ex_width = ew;
+    m.flags |= FLAG_HAS_EX;
+    let bw = m.bit_width;
+    let grow = m.ex_bits(len) - old_bits;
+    if bw > 0 {
+      for (r, &p) in pos.iter().enumerate() {
+        write_bits(
+          &mut self.residuals,
+          bit_off + p as usize * bw as usize,
+          bw,
+          r as u64,
+        );
+      }
+    }
+    self.shift_ex_offsets(b_idx, grow);
+  }
+
+  /// The exception section of block `b_idx` grew by `by` bits; move every
+  /// later block's exception offset along.
+  /// 块 `b_idx` 的异常段增长了 `by` 位；后续各块的异常偏移随之后移。
+  fn shift_ex_offsets(&mut self, b_idx: usize, by: usize) {
+    if by == 0 {
+      return;
+    }
+    if self.anchors.is_empty() {
+      let Some(last) = self.block_meta[b_idx + 1..].last() else {
+        return;
+      };
+      if last.exception_offset as u64 + by as u64 <= u32::MAX as u64 {
+        for m in &mut self.block_meta[b_idx + 1..] {
+          m.exception_offset += by as u32;
+        }
+        return;
+      }
+      // Offsets past u32 switch to large mode, as the builder does
+      // 偏移超过 u32 时切换到大模式，与构建器一致
+      self.anchors = anchor(&mut self.block_meta, super_shift(B));
+    }
+    // Every block's section stays within the bound super_shift is sized for
+    // 每块的异常段都不超过 super_shift 所依据的上界
+    let bump = |m: &mut BlockMeta| {
+      m.exception_offset = u32::try_from(by)
+        .ok()
+        .and_then(|by| m.exception_offset.checked_add(by))
+        .expect("super_shift bounds offsets within a superblock");
+    };
+    // Relative offsets inside the superblock, anchors after it
+    // 超块内改相对偏移，其后改锚点
+    let per = 1usize << super_shift(B);
+    let sup = b_idx / per;
+    let end = ((sup + 1) * per).min(self.block_meta.len());
+    self.block_meta[b_idx + 1..end].iter_mut().for_each(bump);
+    for ex in self.anchors.iter_mut().skip(2 * (sup + 1) + 1).step_by(2) {
+      *ex += by as u64;
+    }
+  }
+
+  /// Re-encode blocks where more than `1 / COMPACT_EX_DIV` of the values are
+  /// exceptions and a fresh first-to-last line and width cost less; other
+  /// blocks are copied bit for bit and block boundaries never move. Returns
+  /// the number of blocks re-encoded, so a second call returns 0.
+  /// 重新编码异常值占比超过 `1 / COMPACT_EX_DIV` 且用新的首末直线与位宽
+  /// 代价更低的块；其余块逐位复制，块边界不变。返回重新编码的块数，
+  /// 因此再次调用返回 0。
+  pub fn compact(&mut self) -> usize {
+    let view = self.view();
+    let mut trial = Enc::<T>::new(0, self.ex_penalty.get(), super_shift(B), false);
+    let mut values = Vec::new();
+    let mut stale = Vec::with_capacity(self.block_count());
+    for (b_idx, meta) in self.block_meta.iter().enumerate() {
+      let (start, end) = view.block_span(b_idx);
+      let ex = meta.ex_count as usize;
+      let worth = ex * COMPACT_EX_DIV > end - start && {
+        values.clear();
+        values.extend(view.iter_range(start..end));
+        let now = trial.cost(end - start, meta.bit_width, ex, meta.ex_width);
+        trial.trial(&values, line_slope(&values)) < now
+      };
+      stale.push(worth);
+    }
+    let rebuilt = stale.iter().filter(|&&s| s).count();
+    if rebuilt > 0 {
+      self.rebuild(|b| stale[b], None);
+    }
+    rebuilt
+  }
+
+  /// Re-encode the blocks `stale` picks on a fresh first-to-last line, with
+  /// `patch` written over its index first; copy every other block unchanged.
+  /// 以新的首末直线重新编码 `stale` 选中的块（先以 `patch` 覆盖其索引处的
+  /// 值），其余块原样复制。
+  fn rebuild(&mut self, stale: impl Fn(usize) -> bool, patch: Option<(usize, T)>) {
+    let view = self.view();
+    let mut enc = Enc::new(
+      self.len,
+      self.ex_penalty.get(),
+      super_shift(B),
+      self.is_large(),
+    );
+    let mut values = Vec::new();
+    for (b_idx, meta) in self.block_meta.iter().enumerate() {
+      let (start, end) = view.block_span(b_idx);
+      if stale(b_idx) {
+        values.clear();
+        values.extend(view.iter_range(start..end));
+        if let Some((i, v)) = patch.filter(|&(i, _)| (start..end).contains(&i)) {
+          values[i - start] = v;
+        }
+        enc.push(&values, line_slope(&values));
+      } else {
+        let (bit_off, ex_off) = view.offsets(b_idx, meta);
+        enc.push_encoded(
+          meta,
+          (&self.residuals, bit_off),
+          (&self.exceptions, ex_off),
+          end - start,
+        );
+      }
+    }
+
+    self.block_meta = enc.block_meta;
+    self.residuals = enc.bit_writer.finish();
+    self.exceptions = enc.ex_writer.finish();
+    self.anchors = enc.anchors;
+  }
+}
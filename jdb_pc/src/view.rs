@@ -0,0 +1,369 @@
+//! Borrowed, zero-copy view of a Pc
+//! Pc 的借用零拷贝视图
+//!
+//! `PcBase` reads through `view()`, and `PcViewBase::new` maps the dumped
+//! bytes (for example an mmapped column file) without copying any section.
+//! `PcBase` 经 `view()` 读取，`PcViewBase::new` 直接映射转储字节
+//! （例如 mmap 的列文件），不复制任何段。
+
+use std::{marker::PhantomData, ops::Range};
+
+use jdb_pgm_lib::{
+  bits::{read_bits, unpack},
+  error::Result,
+};
+
+use super::{
+  PcBase, io,
+  iter::{Cursor, PcIterBase, PcRevIterBase},
+  types::{BlockMeta, BlockMode, ExPenalty, FLAG_HAS_EX, PcInt, pos_width, super_shift},
+};
+
+/// Pc sections borrowed from a `PcBase` or from 8-byte aligned bytes.
+/// 借自 `PcBase` 或 8 字节对齐字节的 Pc 各段。
+#[derive(Clone, Copy, Debug)]
+pub struct PcViewBase<'a, const B: usize, T: PcInt = u64> {
+  pub block_meta: &'a [BlockMeta],
+  pub residuals: &'a [u64],
+  pub exceptions: &'a [u64],
+  pub mode: BlockMode,
+  pub epsilon: usize,
+  pub ex_penalty: ExPenalty,
+  pub starts: &'a [u64],
+  pub dir: &'a [u32],
+  pub dir_shift: u8,
+  pub anchors: &'a [u64],
+  pub len: usize,
+  pub(crate) elem: PhantomData<T>,
+}
+
+impl<'a, const B: usize, T: PcInt> PcViewBase<'a, B, T> {
+  /// Map bytes written by `PcBase::dump`; they must start 8-byte aligned.
+  /// Header, CRC, section lengths and block metadata are validated once,
+  /// nothing is copied.
+  /// 映射 `PcBase::dump` 写出的字节，须 8 字节对齐。
+  /// 文件头、CRC、段长与块元数据只校验一次，不复制任何数据。
+  pub fn new(bytes: &'a [u8]) -> Result<Self> {
+    io::view(bytes)
+  }
+
+  /// As `new`, without the CRC pass over every byte (structure is still
+  /// validated); for files whose integrity is checked elsewhere.
+  /// 同 `new`，但不对全部字节计算 CRC（结构仍会校验）；
+  /// 适用于完整性已在别处校验的文件。
+  pub fn new_skip_crc(bytes: &'a [u8]) -> Result<Self> {
+    io::view_skip_crc(bytes)
+  }
+
+  /// Copy into an owned `PcBase`.
+  /// 复制为自有的 `PcBase`。
+  pub fn to_pc(&self) -> PcBase<B, T> {
+    PcBase {
+      block_meta: self.block_meta.to_vec(),
+      residuals: self.residuals.to_vec(),
+      exceptions: self.exceptions.to_vec(),
+      mode: self.mode,
+      epsilon: self.epsilon,
+      ex_penalty: self.ex_penalty,
+      starts: self.starts.to_vec(),
+      dir: self.dir.to_vec(),
+      dir_shift: self.dir_shift,
+      anchors: self.anchors.to_vec(),
+      len: self.len,
+      elem: PhantomData,
+    }
+  }
+
+  /// Number of elements.
+  /// 元素个数。
+  #[inline]
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  /// Whether there are no elements.
+  /// 是否为空。
+  #[inline]
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  /// Block holding `index` and the block's first index.
+  /// 包含 `index` 的块及该块首索引。
+  #[inline(always)]
+  pub(crate) fn locate(&self, index: usize) -> (usize, usize) {
+    match self.mode {
+      BlockMode::Fixed => {
+        let b_idx = index / B;
+        (b_idx, b_idx * B)
+      }
+      // SAFETY: index < len, so the directory slot exists and the scan stops
+      // before the sentinel `starts[blocks] == len`
+      BlockMode::Epsilon => unsafe {
+        let mut b_idx = *self.dir.get_unchecked(index >> self.dir_shift) as usize;
+        while *self.starts.get_unchecked(b_idx + 1) as usize <= index {
+          b_idx += 1;
+        }
+        (b_idx, *self.starts.get_unchecked(b_idx) as usize)
+      },
+    }
+  }
+
+  /// Index range `[start, end)` covered by block `b_idx`.
+  /// 块 `b_idx` 覆盖的索引区间 `[start, end)`。
+  #[inline(always)]
+  pub(crate) fn block_span(&self, b_idx: usize) -> (usize, usize) {
+    match self.mode {
+      BlockMode::Fixed => (b_idx * B, ((b_idx + 1) * B).min(self.len)),
+      BlockMode::Epsilon => (self.starts[b_idx] as usize, self.starts[b_idx + 1] as usize),
+    }
+  }
+
+  /// Absolute residual bit offset and exception offset of block `b_idx`.
+  /// 块 `b_idx` 的绝对残差位偏移与异常偏移。
+  #[inline(always)]
+  pub(crate) fn offsets(&self, b_idx: usize, meta: &BlockMeta) -> (usize, usize) {
+    let (bit, ex) = (meta.bit_offset as usize, meta.exception_offset as usize);
+    if self.anchors.is_empty() {
+      return (bit, ex);
+    }
+    // SAFETY: validated to hold one pair per superblock
+    let at = (b_idx >> super_shift(B)) * 2;
+    unsafe {
+      (
+        bit + *self.anchors.get_unchecked(at) as usize,
+        ex + *self.anchors.get_unchecked(at + 1) as usize,
+      )
+    }
+  }
+
+  /// Rank of in-block position `k` among the exceptions of a block of
+  /// `len` values, given the code in its residual slot; `None` when the slot
+  /// holds a plain residual. Positions are distinct, so one probe decides.
+  /// 给定残差槽中的码，返回块内位置 `k` 在含 `len` 个值的块的异常值中的秩；
+  /// 槽中为普通残差时返回 `None`。各位置互不相同，一次探测即可判定。
+  #[inline(always)]
+  pub(crate) fn ex_rank(
+    &self,
+    meta: &BlockMeta,
+    ex_off: usize,
+    len: usize,
+    k: usize,
+    slot: u64,
+  ) -> Option<usize> {
+    if slot >= meta.ex_count as u64 {
+      return None;
+    }
+    let pw = pos_width(len);
+    let pos = if pw == 0 {
+      0
+    } else {
+      read_bits(self.exceptions, ex_off + slot as usize * pw as usize, pw)
+    };
+    (pos == k as u64).then_some(slot as usize)
+  }
+
+  /// Code of exception `rank` in a block of `len` values.
+  /// 含 `len` 个值的块中第 `rank` 个异常值的码。
+  #[inline(always)]
+  pub(crate) fn ex_code(&self, meta: &BlockMeta, ex_off: usize, len: usize, rank: usize) -> u64 {
+    let ew = meta.ex_width;
+    if ew == 0 {
+      return 0;
+    }
+    let codes = ex_off + meta.ex_count as usize * pos_width(len) as usize;
+    read_bits(self.exceptions, codes + rank * ew as usize, ew)
+  }
+
+  /// Number of blocks.
+  /// 块数。
+  #[inline]
+  pub fn block_count(&self) -> usize {
+    self.block_meta.len()
+  }
+
+  /// Get value at index.
+  #[inline(always)]
+  pub fn get(&self, index: usize) -> Option<T> {
+    if index >= self.len {
+      return None;
+    }
+    unsafe { Some(self.get_unchecked(index)) }
+  }
+
+  /// Get value at index without bounds check.
+  /// # Safety
+  /// Caller must ensure index < self.len().
+  #[inline(always)]
+  pub unsafe fn get_unchecked(&self, index: usize) -> T {
+    // Step 1: Lookup Meta (1 cache miss)
+    let (b_idx, start) = self.locate(index);
+    // SAFETY: index checked by caller. b_idx valid.
+    let meta = unsafe { self.block_meta.get_unchecked(b_idx) };
+
+    // Step 2: Predict using Block Model (Inline)
+    let pred = meta.predict(index - start);
+
+    // Step 3: Decode Residual (1 cache miss if no flags, 2 if exception)
+    let w = meta.bit_width as usize;
+    if (meta.flags & FLAG_HAS_EX) == 0 {
+      // Hot path: No exceptions
+      let code = if w == 0 {
+        0
+      } else {
+        read_bits(
+          self.residuals,
+          self.offsets(b_idx, meta).0 + (index - start) * w,
+          w as u8,
+        )
+      };
+
+      let res = (code >> 1) as i64 ^ -((code & 1) as i64);
+      T::from_bits(pred.wrapping_add(res as u64))
+    } else {
+      unsafe { self.get_exception_cold(index, b_idx, start, meta, pred) }
+    }
+  }
+
+  #[cold]
+  unsafe fn get_exception_cold(
+    &self,
+    index: usize,
+    b_idx: usize,
+    start: usize,
+    meta: &BlockMeta,
+    pred: u64,
+  ) -> T {
+    let (bit_off, ex_off) = self.offsets(b_idx, meta);
+    let k = index - start;
+    let w = meta.bit_width;
+    let slot = if w == 0 {
+      0
+    } else {
+      read_bits(self.residuals, bit_off + k * w as usize, w)
+    };
+    // An exception's slot holds its rank; a plain residual is the code itself
+    // 异常值的槽存其秩；普通残差即码本身
+    let len = self.block_span(b_idx).1 - start;
+    let code = match self.ex_rank(meta, ex_off, len, k, slot) {
+      Some(rank) => self.ex_code(meta, ex_off, len, rank),
+      None => slot,
+    };
+    // Exceptions hold the zigzag code too, not the value
+    // 异常值存的同样是 zigzag 码而非原值
+    let res = (code >> 1) as i64 ^ -((code & 1) as i64);
+    T::from_bits(pred.wrapping_add(res as u64))
+  }
+
+  /// Decode `lo..hi`, which lies inside block `b_idx`, into `out`; `codes`
+  /// is scratch space for the raw residual codes, unpacked in bulk.
+  /// 将位于块 `b_idx` 内的 `lo..hi` 解码到 `out`；`codes` 用作原始残差码的
+  /// 暂存区，按批解包。
+  pub(crate) fn decode_span(
+    &self,
+    b_idx: usize,
+    lo: usize,
+    hi: usize,
+    codes: &mut Vec<u64>,
+    out: &mut [T],
+  ) {
+    let meta = &self.block_meta[b_idx];
+    let (start, end) = self.block_span(b_idx);
+    let (bit_off, ex_off) = self.offsets(b_idx, meta);
+    let w = meta.bit_width;
+    let (span, n) = (hi - lo, meta.ex_count as usize);
+    let ex = if meta.flags & FLAG_HAS_EX != 0 { n } else { 0 };
+    // Residual codes, then the block's exception positions and codes
+    // 残差码，其后为该块的异常位置与异常码
+    codes.resize(span + 2 * ex, 0);
+    let (vals, rest) = codes.split_at_mut(span);
+    unpack(self.residuals, bit_off + (lo - start) * w as usize, w, vals);
+
+    if ex > 0 {
+      let (pos, ex_codes) = rest.split_at_mut(ex);
+      let pw = pos_width(end - start);
+      unpack(self.exceptions, ex_off, pw, pos);
+      unpack(
+        self.exceptions,
+        ex_off + ex * pw as usize,
+        meta.ex_width,
+        ex_codes,
+      );
+      let first = (lo - start) as u64;
+      for (&p, &code) in pos.iter().zip(ex_codes.iter()) {
+        if let Some(v) = vals.get_mut(p.wrapping_sub(first) as usize) {
+          *v = code;
+        }
+      }
+    }
+
+    // Block local model, stepped by accumulating the slope
+    // 块内模型，通过累加斜率逐步推进
+    let (base, slope, shift) = (meta.base, meta.slope, meta.shift);
+    let mut acc = ((lo - start) as i64).wrapping_mul(slope);
+    for (o, &code) in out.iter_mut().zip(codes.iter()) {
+      let res = (code >> 1) as i64 ^ -((code & 1) as i64);
+      *o = T::from_bits(
+        base
+          .wrapping_add((acc >> shift) as u64)
+          .wrapping_add(res as u64),
+      );
+      acc = acc.wrapping_add(slope);
+    }
+  }
+
+  /// Decode `range` into `out`, a block at a time.
+  /// 按块将 `range` 解码到 `out`。
+  ///
+  /// # Panics
+  /// If `range` is out of bounds or `out.len() != range.len()`.
+  pub fn get_range_into(&self, out: &mut [T], range: Range<usize>) {
+    assert!(
+      range.start <= range.end && range.end <= self.len,
+      "range {range:?} out of bounds for Pc of length {}",
+      self.len
+    );
+    assert_eq!(out.len(), range.len(), "output length does not match range");
+    if range.is_empty() {
+      return;
+    }
+    let mut codes = Vec::with_capacity(B.min(range.len()));
+    let (mut b_idx, _) = self.locate(range.start);
+    let mut at = range.start;
+    while at < range.end {
+      let hi = self.block_span(b_idx).1.min(range.end);
+      let dst = &mut out[at - range.start..hi - range.start];
+      self.decode_span(b_idx, at, hi, &mut codes, dst);
+      at = hi;
+      b_idx += 1;
+    }
+  }
+
+  /// Create forward iterator starting from index 0.
+  pub fn iter(&self) -> PcIterBase<'a, B, T> {
+    self.iter_range(0..self.len)
+  }
+
+  /// Create forward iterator for range.
+  pub fn iter_range(&self, range: Range<usize>) -> PcIterBase<'a, B, T> {
+    let start = range.start.min(self.len);
+    let end = range.end.min(self.len);
+    PcIterBase {
+      cur: Cursor::new(*self, start.min(end), end),
+    }
+  }
+
+  /// Create reverse iterator starting from the last element.
+  pub fn rev_iter(&self) -> PcRevIterBase<'a, B, T> {
+    self.rev_iter_range(0..self.len)
+  }
+
+  /// Create reverse iterator for range, iterating from end-1 down to start.
+  pub fn rev_iter_range(&self, range: Range<usize>) -> PcRevIterBase<'a, B, T> {
+    let start = range.start.min(self.len);
+    let end = range.end.min(self.len);
+    PcRevIterBase {
+      cur: Cursor::new(*self, start.min(end), end),
+    }
+  }
+}
@@ -55,3 +55,937 @@ fn test_pc_correctness_random() {
     }
   }
 }
+
+#[test]
+fn test_epsilon_blocks() {
+  use jdb_pc::types::{BlockMode, PcConf};
+  use rand::prelude::*;
+
+  let mut rng = StdRng::seed_from_u64(7);
+  // Piecewise linear runs with small noise, then a jump
+  // 带小噪声的分段线性区间，之后跳变
+  let mut data = Vec::with_capacity(20_000);
+  let mut base = 1_000u64;
+  while data.len() < 20_000 {
+    let step = rng.random_range(1..50u64);
+    let run = rng.random_range(1..600usize);
+    for i in 0..run as u64 {
+      data.push(base + i * step + rng.random_range(0..4));
+    }
+    base += run as u64 * step + rng.random_range(0..100_000);
+  }
+
+  let eps = 4;
+  // The epsilon constructors cut blocks by epsilon
+  // 以 epsilon 构造时按 epsilon 分块
+  let conf = PcConf::with_epsilon(eps);
+  let pc = Pc::new_with_conf(&data, conf);
+  assert_eq!(pc.mode, BlockMode::Epsilon);
+  assert_eq!(Pc::new(&data, eps).starts, pc.starts);
+  assert_eq!(PcConf::default().mode, BlockMode::Epsilon);
+  assert_eq!(pc.starts.len(), pc.block_count() + 1);
+
+  // Each block keeps its residuals within ±eps (plus fixed-point rounding)
+  // 每块残差保持在 ±eps 内（外加定点舍入）
+  for meta in &pc.block_meta {
+    assert!(meta.bit_width <= 5, "width {}", meta.bit_width);
+  }
+
+  for (i, &v) in data.iter().enumerate() {
+    assert_eq!(pc.get(i), Some(v), "index {i}");
+  }
+  assert_eq!(pc.get(data.len()), None);
+  assert_eq!(pc.iter().collect::<Vec<_>>(), data);
+  let rev: Vec<u64> = pc.rev_iter().collect();
+  assert!(rev.iter().eq(data.iter().rev()));
+  for _ in 0..200 {
+    let a = rng.random_range(0..data.len());
+    let b = rng.random_range(a..=data.len());
+    assert_eq!(pc.iter_range(a..b).collect::<Vec<_>>(), data[a..b]);
+  }
+
+  // Mode and directory survive a round trip
+  // 模式与目录可往返
+  let loaded = Pc::load(&pc.dump()).unwrap();
+  assert_eq!(loaded.mode, BlockMode::Epsilon);
+  assert_eq!(loaded.starts, pc.starts);
+  assert_eq!(loaded.dir, pc.dir);
+  assert_eq!(loaded.iter().collect::<Vec<_>>(), data);
+
+  for size in [1, 2, 3, 1000] {
+    let small = &data[..size];
+    let pc = Pc::new_with_conf(small, conf);
+    assert_eq!(pc.iter().collect::<Vec<_>>(), small);
+    assert_eq!(pc.get(size - 1), Some(small[size - 1]));
+  }
+  let empty = Pc::new_with_conf(&[], conf);
+  assert_eq!(empty.iter().count(), 0);
+  assert_eq!(Pc::load(&empty.dump()).unwrap().len, 0);
+
+  // Noise no line can follow falls back to fixed blocks with exceptions
+  // instead of a block meta every few values
+  // 任何直线都跟不上的噪声退回带异常值的固定块，而非每几个值一个块元数据
+  let mut noisy: Vec<u64> = (0..50_000)
+    .map(|_| rng.random_range(0..1u64 << 40))
+    .collect();
+  noisy.sort_unstable();
+  let pc = Pc::new_with_conf(&noisy, conf);
+  let fixed = Pc::new_with_conf(&noisy, conf.with_mode(BlockMode::Fixed));
+  let block = jdb_pc::types::BLOCK_LEN;
+  assert!(
+    pc.starts
+      .windows(2)
+      .rev()
+      .skip(1)
+      .all(|w| w[1] - w[0] >= (block / 4) as u64),
+    "short epsilon blocks"
+  );
+  assert!(pc.size_in_bytes() * 4 < fixed.size_in_bytes() * 5);
+  assert_eq!(pc.iter().collect::<Vec<_>>(), noisy);
+}
+
+#[test]
+fn test_search() {
+  use jdb_pc::types::{BlockMode, PcConf};
+  use rand::prelude::*;
+
+  let mut rng = StdRng::seed_from_u64(11);
+  // Sorted with duplicates and gaps
+  // 有序，含重复与空洞
+  let mut data: Vec<u64> = (0..30_000)
+    .map(|_| rng.random_range(10..1_000_000))
+    .collect();
+  data.sort_unstable();
+
+  for mode in [BlockMode::Fixed, BlockMode::Epsilon] {
+    let pc = Pc::new_with_conf(&data, PcConf::with_epsilon(16).with_mode(mode));
+    // Exceptions decode through get() as well as iter()
+    // 异常值经 get() 与 iter() 均能正确解码
+    assert!(mode == BlockMode::Epsilon || pc.block_meta.iter().any(|m| m.flags != 0));
+    assert!((0..data.len()).all(|i| pc.get(i) == Some(data[i])));
+    let probes = (0..20_000)
+      .map(|_| rng.random_range(0..1_000_010))
+      .chain(data.iter().step_by(7).copied())
+      .chain([0, 10, u64::MAX, data[data.len() - 1]]);
+    for v in probes {
+      let expect = data.partition_point(|&x| x < v);
+      assert_eq!(pc.lower_bound(v), expect, "{mode:?} v={v}");
+      assert_eq!(pc.rank(v), expect);
+      assert_eq!(pc.contains(v), data.binary_search(&v).is_ok());
+    }
+  }
+
+  let empty = Pc::new(&[], 16);
+  assert_eq!(empty.lower_bound(5), 0);
+  assert!(!empty.contains(0));
+  let one = Pc::new(&[42], 16);
+  assert_eq!(one.lower_bound(42), 0);
+  assert_eq!(one.lower_bound(43), 1);
+  assert!(one.contains(42));
+}
+
+/// Copy bytes into an 8-byte aligned buffer, as an mmap would provide
+/// 将字节复制到 8 字节对齐的缓冲区，如同 mmap 所提供
+fn aligned(bytes: &[u8]) -> Vec<u64> {
+  let mut words = vec![0u64; bytes.len().div_ceil(8)];
+  for (w, chunk) in words.iter_mut().zip(bytes.chunks(8)) {
+    let mut buf = [0u8; 8];
+    buf[..chunk.len()].copy_from_slice(chunk);
+    *w = u64::from_le_bytes(buf);
+  }
+  words
+}
+
+fn as_bytes(words: &[u64], len: usize) -> &[u8] {
+  unsafe { std::slice::from_raw_parts(words.as_ptr().cast::<u8>(), len) }
+}
+
+#[test]
+fn test_view() {
+  use jdb_pc::{
+    PcBase, PcView,
+    io::{self, HEAD_LEN},
+    types::{BlockMode, PcConf},
+  };
+  use rand::prelude::*;
+
+  let mut rng = StdRng::seed_from_u64(5);
+  let mut data: Vec<u64> = (0..10_000)
+    .map(|_| rng.random_range(0..1u64 << 40))
+    .collect();
+  data.sort_unstable();
+
+  for mode in [BlockMode::Fixed, BlockMode::Epsilon] {
+    let pc = Pc::new_with_conf(&data, PcConf::with_epsilon(64).with_mode(mode));
+    let bytes = pc.dump();
+    assert_eq!(bytes.len() % 8, 0);
+    let words = aligned(&bytes);
+    let view = PcView::new(as_bytes(&words, bytes.len())).unwrap();
+
+    assert_eq!(view.len(), data.len());
+    assert_eq!(view.mode, mode);
+    assert!((0..data.len()).all(|i| view.get(i) == Some(data[i])));
+    assert_eq!(view.get(data.len()), None);
+    assert_eq!(view.iter().collect::<Vec<_>>(), data);
+    assert_eq!(
+      view.iter_range(100..900).collect::<Vec<_>>(),
+      data[100..900]
+    );
+    assert!(view.rev_iter().eq(data.iter().rev().copied()));
+    for &v in data.iter().step_by(97) {
+      assert_eq!(view.lower_bound(v), pc.lower_bound(v));
+    }
+    assert_eq!(view.to_pc().iter().collect::<Vec<_>>(), data);
+
+    // load accepts any alignment
+    // load 接受任意对齐
+    let mut shifted = vec![0u8];
+    shifted.extend_from_slice(&bytes);
+    assert_eq!(
+      Pc::load(&shifted[1..]).unwrap().iter().collect::<Vec<_>>(),
+      data
+    );
+
+    // The copying reader, used on big-endian targets, rebuilds the same Pc
+    // 大端目标使用的复制读取器重建出相同的 Pc
+    let copied = io::load_copy::<128, u64>(&shifted[1..]).unwrap();
+    assert_eq!(copied.residuals, pc.residuals);
+    assert_eq!(copied.exceptions, pc.exceptions);
+    assert_eq!(copied.starts, pc.starts);
+    assert_eq!(copied.dir, pc.dir);
+    assert_eq!((copied.mode, copied.epsilon), (pc.mode, pc.epsilon));
+    assert_eq!(copied.iter().collect::<Vec<_>>(), data);
+  }
+
+  let pc = Pc::new(&data, 16);
+  let bytes = pc.dump();
+  let words = aligned(&bytes);
+  let good = as_bytes(&words, bytes.len());
+
+  // Views refuse unaligned or truncated bytes
+  // 视图拒绝未对齐或截断的字节
+  let mut shifted = vec![0u8];
+  shifted.extend_from_slice(&bytes);
+  let shifted_words = aligned(&shifted);
+  assert!(PcView::new(&as_bytes(&shifted_words, shifted.len())[1..]).is_err());
+  assert!(PcView::new(&good[..good.len() - 8]).is_err());
+  assert!(PcView::new(&good[..10]).is_err());
+
+  // Any flipped byte fails the checksum
+  // 任一字节翻转都无法通过校验和
+  for at in [0, 5, 20, 80, bytes.len() / 2, bytes.len() - 8] {
+    let mut b = bytes.clone();
+    b[at] ^= 0x10;
+    let w = aligned(&b);
+    assert!(PcView::new(as_bytes(&w, b.len())).is_err(), "at={at}");
+    assert!(Pc::load(&b).is_err(), "at={at}");
+    assert!(io::load_copy::<128, u64>(&b).is_err(), "at={at}");
+  }
+
+  // Without the CRC pass, corrupt counts and block metadata are still caught
+  // 跳过 CRC 时，损坏的段长与块元数据仍会被发现
+  let corrupt = |at: usize, val: u64| {
+    let mut b = bytes.clone();
+    b[at..at + 8].copy_from_slice(&val.to_le_bytes());
+    let w = aligned(&b);
+    PcView::new_skip_crc(as_bytes(&w, b.len())).is_err()
+  };
+  assert!(!corrupt(24, data.len() as u64));
+  assert!(corrupt(24, u64::MAX));
+  assert!(corrupt(32, 1 << 40));
+  // First block's bit_offset and exception_offset
+  // 首块的 bit_offset 与 exception_offset
+  assert!(corrupt(HEAD_LEN, u32::MAX as u64));
+
+  // Another block length cannot reinterpret the blocks
+  // 不同的块长无法错误解读这些块
+  let err = PcBase::<64>::load(&bytes).unwrap_err().to_string();
+  assert!(err.contains("Block length 128"), "{err}");
+  // Unknown version, element type, flags and a zero penalty are rejected
+  // 拒绝未知的版本、元素类型、标志与为零的惩罚系数
+  for (at, val) in [(4, 9u8), (7, 4), (12, 2), (14, 0)] {
+    let mut b = bytes[..bytes.len() - 8].to_vec();
+    b[at] = val;
+    let crc = crc32fast::hash(&b);
+    b.extend_from_slice(&crc.to_le_bytes());
+    b.extend_from_slice(&[0; 4]);
+    assert!(Pc::load(&b).is_err(), "at={at}");
+  }
+
+  let empty = Pc::new(&[], 16).dump();
+  let w = aligned(&empty);
+  let view = PcView::new(as_bytes(&w, empty.len())).unwrap();
+  assert!(view.is_empty());
+  assert_eq!(view.iter().count(), 0);
+}
+
+/// Round-trip one element type through get, iterators, search, dump and view
+/// 让一种元素类型往返 get、迭代器、查找、转储与视图
+fn check_elem<T: jdb_pc::types::PcInt>(data: &[T], sorted: bool) {
+  use jdb_pc::{PcOf, PcViewOf, types::BlockMode, types::PcConf};
+
+  for mode in [BlockMode::Fixed, BlockMode::Epsilon] {
+    let pc = PcOf::<T>::new_with_conf(data, PcConf::with_epsilon(8).with_mode(mode));
+    assert!(
+      (0..data.len()).all(|i| pc.get(i) == Some(data[i])),
+      "{mode:?}"
+    );
+    assert_eq!(pc.iter().collect::<Vec<_>>(), data);
+    assert!(pc.rev_iter().eq(data.iter().rev().copied()));
+    if sorted {
+      for &v in data.iter().step_by(13) {
+        assert_eq!(pc.lower_bound(v), data.partition_point(|&x| x < v));
+        assert!(pc.contains(v));
+      }
+    }
+
+    let bytes = pc.dump();
+    let loaded = PcOf::<T>::load(&bytes).unwrap();
+    assert_eq!(loaded.exceptions, pc.exceptions);
+    assert_eq!(loaded.iter().collect::<Vec<_>>(), data);
+    let words = aligned(&bytes);
+    let view = PcViewOf::<T>::new(as_bytes(&words, bytes.len())).unwrap();
+    assert_eq!(view.iter().collect::<Vec<_>>(), data);
+  }
+}
+
+#[test]
+fn test_element_types() {
+  use jdb_pc::PcOf;
+  use rand::prelude::*;
+
+  let mut rng = StdRng::seed_from_u64(3);
+
+  // u32 offsets with a few far outliers
+  // 带少量远离群值的 u32 偏移
+  let mut off = 0u32;
+  let offsets: Vec<u32> = (0..5_000)
+    .map(|i| {
+      off += rng.random_range(10..200);
+      if i % 500 == 7 { u32::MAX - i } else { off }
+    })
+    .collect();
+  check_elem(&offsets, false);
+
+  // i64 deltas around zero, i32 sensor samples across the whole range
+  // 零附近的 i64 增量，覆盖全范围的 i32 传感器采样
+  let deltas: Vec<i64> = (0..5_000)
+    .map(|_| rng.random_range(-1_000..1_000))
+    .collect();
+  check_elem(&deltas, false);
+  let mut samples: Vec<i32> = (0..5_000).map(|_| rng.random()).collect();
+  samples.extend([i32::MIN, i32::MAX, 0, -1, i32::MIN, i32::MAX]);
+  check_elem(&samples, false);
+  samples.sort_unstable();
+  check_elem(&samples, true);
+
+  let small: Vec<u8> = (0..3_000).map(|i| (i / 12) as u8).collect();
+  check_elem(&small, true);
+  let signed16: Vec<i16> = (0..3_000).map(|i| (i as i16).wrapping_mul(-23)).collect();
+  check_elem(&signed16, false);
+
+  // Exceptions keep the native width instead of widening to u64
+  // 异常值保持原生宽度，不再扩宽为 u64
+  let pc32 = PcOf::<u32>::new(&offsets, 8);
+  let wide: Vec<u64> = offsets.iter().map(|&v| v as u64).collect();
+  let pc64 = Pc::new(&wide, 8);
+  assert!(!pc32.exceptions.is_empty());
+  assert!(size_of_val(&pc32.exceptions[..]) < size_of_val(&pc64.exceptions[..]));
+  assert!(pc32.size_in_bytes() < pc64.size_in_bytes());
+
+  // Bytes of one element type do not load as another
+  // 一种元素类型的字节不能按另一种读取
+  let bytes = pc32.dump();
+  assert!(PcOf::<i32>::load(&bytes).is_err());
+  assert!(Pc::load(&bytes).is_err());
+}
+
+#[test]
+fn test_legacy_formats() {
+  use jdb_pc::{PcBase, PcOf, PcView, types::BlockMode};
+
+  // Written by the header-less release: global lines and an exception
+  // bitmap; `load` re-encodes it, views refuse it
+  // 由无文件头的版本写出：全局直线与异常位图；`load` 会重新编码，视图拒绝
+  let data: Vec<u64> = (0..1000u64)
+    .map(|i| i * i * 37 + if i % 97 == 0 { 1 << 40 } else { 0 })
+    .collect();
+  let bytes: &[u8] = include_bytes!("data/pc_v0.bin");
+  let pc = Pc::load(bytes).unwrap();
+  assert_eq!(pc.mode, BlockMode::Fixed);
+  assert!(!pc.is_large());
+  assert_eq!(pc.iter().collect::<Vec<_>>(), data);
+  assert!((0..data.len()).all(|i| pc.get(i) == Some(data[i])));
+  let w = aligned(bytes);
+  assert!(PcView::new(as_bytes(&w, bytes.len())).is_err());
+
+  // Re-dumped with a header, now viewable
+  // 带文件头重新转储后即可视图
+  let again = pc.dump();
+  let w = aligned(&again);
+  assert_eq!(
+    PcView::new(as_bytes(&w, again.len())).unwrap().get(500),
+    Some(data[500])
+  );
+
+  assert!(PcBase::<64>::load(bytes).is_err());
+  assert!(PcOf::<u32>::load(bytes).is_err());
+  // Truncated or extended bytes are rejected
+  // 拒绝截断或追加的字节
+  assert!(Pc::load(&bytes[..bytes.len() - 1]).is_err());
+  let mut longer = bytes.to_vec();
+  longer.push(0);
+  assert!(Pc::load(&longer).is_err());
+}
+
+#[test]
+fn test_signed_slopes() {
+  use jdb_pc::{PcOf, types::BlockMode, types::PcConf};
+
+  for mode in [BlockMode::Fixed, BlockMode::Epsilon] {
+    let conf = PcConf::with_epsilon(8).with_mode(mode);
+
+    // Decreasing runs fit a negative slope as tightly as increasing ones
+    // 递减序列与递增序列一样能用负斜率紧密拟合
+    let down: Vec<u64> = (0..20_000u64).map(|i| (1 << 50) - i * 1_000_003).collect();
+    let pc = Pc::new_with_conf(&down, conf);
+    assert_eq!(pc.iter().collect::<Vec<_>>(), down);
+    assert!(pc.block_meta.iter().all(|m| m.bit_width <= 2), "{mode:?}");
+    let up: Vec<u64> = down.iter().rev().copied().collect();
+    let pc_up = Pc::new_with_conf(&up, conf);
+    assert_eq!(pc.residuals.len(), pc_up.residuals.len(), "{mode:?}");
+
+    // Values near the top of the range, far from any global intercept
+    // 接近取值上限的值，远离任何全局截距
+    let high: Vec<u64> = (0..5_000u64).map(|i| u64::MAX - 3 * i * i).collect();
+    let pc = Pc::new_with_conf(&high, conf);
+    assert!((0..high.len()).all(|i| pc.get(i) == Some(high[i])));
+    assert!(pc.rev_iter().eq(high.iter().rev().copied()));
+    assert_eq!(
+      Pc::load(&pc.dump()).unwrap().iter().collect::<Vec<_>>(),
+      high
+    );
+
+    let extremes: Vec<i64> = (0..3_000i64)
+      .map(|i| {
+        if i % 2 == 0 {
+          i64::MIN + i
+        } else {
+          i64::MAX - i
+        }
+      })
+      .collect();
+    let pc = PcOf::<i64>::new_with_conf(&extremes, conf);
+    assert_eq!(pc.iter().collect::<Vec<_>>(), extremes);
+    assert!((0..extremes.len()).all(|i| pc.get(i) == Some(extremes[i])));
+    let falling: Vec<i64> = (0..3_000i64).map(|i| i64::MAX - i * (1 << 40)).collect();
+    let pc = PcOf::<i64>::new_with_conf(&falling, conf);
+    assert_eq!(pc.iter().collect::<Vec<_>>(), falling);
+    assert!(pc.block_meta.iter().all(|m| m.bit_width <= 2), "{mode:?}");
+  }
+}
+
+#[test]
+fn test_large_mode() {
+  use jdb_pc::{
+    PcBase, PcView,
+    types::{BlockMode, MAX_BLOCK_MUL, PcConf, pos_width, super_shift},
+  };
+
+  // A superblock of all-exception blocks still fits u32 offsets
+  // 全为异常值的超块偏移仍能放进 u32
+  for b in [64, 128, 256, 1024] {
+    let len = b * MAX_BLOCK_MUL;
+    let worst = (1usize << super_shift(b)) * len * (pos_width(len) as usize + 64);
+    assert!(worst <= 1 << 32, "{b}");
+  }
+
+  // 9M values span two superblocks of 1 << super_shift(128) blocks
+  // 900 万个值跨越两个含 1 << super_shift(128) 块的超块
+  let data: Vec<u64> = (0..9_000_000u64)
+    .map(|i| i * 13 + (i * i) % 1_000 + if i % 4_099 == 0 { 1 << 45 } else { 0 })
+    .collect();
+  for mode in [BlockMode::Fixed, BlockMode::Epsilon] {
+    let conf = PcConf::with_epsilon(16).with_mode(mode);
+    let small = Pc::new_with_conf(&data, conf);
+    let pc = Pc::new_with_conf(&data, conf.with_large());
+    assert!(!small.is_large());
+    assert!(pc.is_large());
+    assert!(pc.anchors.len() >= 4, "{mode:?}");
+    assert_eq!(pc.residuals, small.residuals);
+    assert_eq!(pc.exceptions, small.exceptions);
+
+    for i in (0..data.len()).step_by(997).chain([data.len() - 1]) {
+      assert_eq!(pc.get(i), Some(data[i]), "{mode:?} {i}");
+    }
+    assert!(
+      pc.iter_range(8_000_000..8_300_000)
+        .eq(data[8_000_000..8_300_000].iter().copied())
+    );
+    assert!(
+      pc.rev_iter()
+        .take(10_000)
+        .eq(data.iter().rev().take(10_000).copied())
+    );
+
+    let bytes = pc.dump();
+    let loaded = Pc::load(&bytes).unwrap();
+    assert!(loaded.is_large());
+    assert_eq!(loaded.anchors, pc.anchors);
+    let copied = jdb_pc::io::load_copy::<128, u64>(&bytes).unwrap();
+    assert_eq!(copied.anchors, pc.anchors);
+    assert_eq!(copied.get(8_999_999), Some(data[8_999_999]));
+    let w = aligned(&bytes);
+    let view = PcView::new(as_bytes(&w, bytes.len())).unwrap();
+    assert_eq!(view.get(8_999_999), Some(data[8_999_999]));
+    assert!(PcBase::<64>::load(&bytes).is_err());
+
+    // An anchor past the residuals is rejected without the CRC pass
+    // 越过残差的锚点即使跳过 CRC 也会被拒绝
+    let mut b = bytes.clone();
+    let at = bytes.len() - 8 - 16;
+    b[at..at + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+    let w = aligned(&b);
+    assert!(PcView::new_skip_crc(as_bytes(&w, b.len())).is_err());
+  }
+}
+
+#[test]
+fn test_builder_and_append() {
+  use jdb_pc::{
+    PcBuilder, PcBuilderOf, PcOf,
+    types::{BlockMode, ExPenalty, PcConf},
+  };
+
+  let mut rng = StdRng::seed_from_u64(11);
+  let mut data = Vec::with_capacity(30_000);
+  let mut cur = 0u64;
+  while data.len() < 30_000 {
+    cur += rng.random_range(0..40);
+    if rng.random_range(0..500) == 0 {
+      cur += 1 << 36;
+    }
+    data.push(cur);
+  }
+
+  // The build epsilon and penalty are kept for appends and in the bytes
+  // 构建时的 epsilon 与惩罚系数在追加时沿用，并写入字节
+  let confs = [PcConf::default(), PcConf::new(2, ExPenalty::new(5))];
+  for conf in confs
+    .into_iter()
+    .flat_map(|c| [BlockMode::Fixed, BlockMode::Epsilon].map(|mode| c.with_mode(mode)))
+  {
+    let mode = conf.mode;
+    let whole = Pc::new_with_conf(&data, conf).dump();
+    let loaded = Pc::load(&whole).unwrap();
+    assert_eq!(
+      (loaded.epsilon, loaded.ex_penalty.get()),
+      (conf.epsilon, conf.ex_penalty.get())
+    );
+
+    // One value at a time, and in uneven chunks, encode the same bytes
+    // 逐个推入或按不均匀的批次推入，编码出相同的字节
+    let mut one = PcBuilder::new(conf);
+    one.extend(data.iter().copied());
+    assert_eq!(one.len(), data.len());
+    assert_eq!(one.finish().dump(), whole, "{mode:?}");
+
+    let mut chunked = PcBuilder::new(conf);
+    let mut rest = &data[..];
+    while !rest.is_empty() {
+      let n = rng.random_range(0..3_000).min(rest.len());
+      chunked.extend_from_slice(&rest[..n]);
+      rest = &rest[n..];
+    }
+    assert_eq!(chunked.finish().dump(), whole, "{mode:?}");
+
+    // Appending reopens the last block, so growth matches a full rebuild
+    // 追加会重新打开最后一块，因此增长结果与完整重建一致
+    let mut pc = Pc::new_with_conf(&[], conf);
+    let mut at = 0;
+    for n in [1, 127, 1, 500, 2_000, 0, 129, 7_000] {
+      pc.append(&data[at..at + n]);
+      at += n;
+      assert_eq!(
+        pc.dump(),
+        Pc::new_with_conf(&data[..at], conf).dump(),
+        "{mode:?} {at}"
+      );
+    }
+    pc.append(&data[at..]);
+    assert_eq!(pc.dump(), whole, "{mode:?}");
+    let mut reloaded = Pc::load(&whole[..]).unwrap();
+    reloaded.append(&data[..100]);
+    let mut longer = data.clone();
+    longer.extend_from_slice(&data[..100]);
+    assert_eq!(reloaded.dump(), Pc::new_with_conf(&longer, conf).dump());
+
+    // Large mode survives reopening, and can be switched on midway
+    // 大模式在重新打开后保留，也可中途开启
+    let mut large = Pc::new_with_conf(&data[..10_000], conf.with_large());
+    large.append(&data[10_000..]);
+    assert!(large.is_large());
+    assert_eq!(large.iter().collect::<Vec<_>>(), data);
+    let mut builder = Pc::new_with_conf(&data[..10_000], conf).into_builder_with(conf.with_large());
+    builder.extend_from_slice(&data[10_000..]);
+    let pc = builder.finish();
+    assert!(pc.is_large());
+    assert_eq!(pc.iter().collect::<Vec<_>>(), data);
+    assert_eq!(
+      Pc::load(&pc.dump()).unwrap().get(29_999),
+      Some(data[29_999])
+    );
+
+    let narrow: Vec<i16> = (0..5_000).map(|i| (i * 7 % 3_001) as i16 - 1_500).collect();
+    let mut b = PcBuilderOf::<i16>::new(conf);
+    for &v in &narrow {
+      b.push(v);
+    }
+    let mut pc = b.finish();
+    assert_eq!(pc.dump(), PcOf::<i16>::new_with_conf(&narrow, conf).dump());
+    pc.append(&narrow);
+    assert!(pc.iter().eq(narrow.iter().chain(&narrow).copied()));
+  }
+}
+
+#[test]
+fn test_set_and_compact() {
+  use jdb_pc::{PcOf, PcView, types::BlockMode, types::PcConf};
+
+  let mut rng = StdRng::seed_from_u64(13);
+  // Residuals wide enough that a block can rank a whole block of exceptions
+  // 残差足够宽，使一个块能给整块的异常值编秩
+  let base: Vec<u64> = (0..20_000u64).map(|i| i * 500 + i * i % 251).collect();
+
+  for (mode, large) in [
+    (BlockMode::Fixed, false),
+    (BlockMode::Epsilon, false),
+    (BlockMode::Fixed, true),
+    (BlockMode::Epsilon, true),
+  ] {
+    let mut conf = PcConf::with_epsilon(8).with_mode(mode);
+    if large {
+      conf = conf.with_large();
+    }
+    let mut data = base.clone();
+    let mut pc = Pc::new_with_conf(&data, conf);
+    assert_eq!(pc.compact(), 0);
+    let ex_before = pc.exception_count();
+
+    // Small nudges stay in place, large jumps become exceptions, and
+    // exceptions can be overwritten again
+    // 小幅改动原地写入，大幅跳变成为异常值，异常值也可再次覆写
+    for round in 0..3_000 {
+      let i = rng.random_range(0..data.len());
+      let v = match round % 3 {
+        0 => data[i] ^ 1,
+        1 => rng.random(),
+        _ => data[i].wrapping_add(1 << 50),
+      };
+      data[i] = v;
+      pc.set(i, v);
+      assert_eq!(pc.get(i), Some(v));
+    }
+    assert!(pc.exception_count() > ex_before);
+    assert!(
+      (0..data.len()).all(|i| pc.get(i) == Some(data[i])),
+      "{mode:?}"
+    );
+    assert_eq!(pc.iter().collect::<Vec<_>>(), data);
+    assert!(pc.rev_iter().eq(data.iter().rev().copied()));
+
+    let bytes = pc.dump();
+    assert_eq!(Pc::load(&bytes).unwrap().iter().collect::<Vec<_>>(), data);
+    let w = aligned(&bytes);
+    let view = PcView::new(as_bytes(&w, bytes.len())).unwrap();
+    assert!(
+      (0..data.len())
+        .step_by(31)
+        .all(|i| view.get(i) == Some(data[i]))
+    );
+
+    // Dense damage in one region is re-encoded, the rest is untouched
+    // 某区域的密集改动会被重新编码，其余保持不变
+    for (i, v) in data.iter_mut().enumerate().skip(5_000).take(600) {
+      *v = rng.random();
+      pc.set(i, *v);
+    }
+    let ex_dirty = pc.exception_count();
+    let rebuilt = pc.compact();
+    assert!(rebuilt > 0, "{mode:?}");
+    assert!(pc.exception_count() < ex_dirty);
+    assert_eq!(pc.is_large(), large);
+    assert_eq!(pc.iter().collect::<Vec<_>>(), data);
+    assert!((0..data.len()).all(|i| pc.get(i) == Some(data[i])));
+    assert_eq!(pc.compact(), 0);
+    assert_eq!(
+      Pc::load(&pc.dump()).unwrap().iter().collect::<Vec<_>>(),
+      data
+    );
+  }
+
+  // A zero-width block ranks one exception; the next re-encodes the block
+  // 零位宽的块只能给一个异常值编秩；下一个会使该块重新编码
+  let mut flat = vec![7u64; 1_000];
+  let mut pc = Pc::new(&flat, 8);
+  assert!(pc.block_meta.iter().all(|m| m.bit_width == 0));
+  for (i, v) in [(3, 1 << 40), (3, u64::MAX), (5, 9), (900, 0), (3, 7)] {
+    flat[i] = v;
+    pc.set(i, v);
+    assert_eq!(pc.get(i), Some(v));
+  }
+  assert_eq!(pc.iter().collect::<Vec<_>>(), flat);
+  assert!(pc.block_meta[0].bit_width > 0);
+
+  let mut small: Vec<i16> = (0..3_000).map(|i| (i / 3) as i16).collect();
+  let mut pc = PcOf::<i16>::new(&small, 8);
+  for (i, v) in [(0, i16::MIN), (1_500, i16::MAX), (2_999, -1), (1_500, 7)] {
+    small[i] = v;
+    pc.set(i, v);
+  }
+  assert_eq!(pc.iter().collect::<Vec<_>>(), small);
+
+  // Rewritten block heads stay the block anchors that searches compare against
+  // 改写后的块首仍是查找所比较的块锚点
+  let mut sorted = base.clone();
+  let mut pc = Pc::new(&sorted, 8);
+  for i in (64..sorted.len()).step_by(64) {
+    sorted[i] -= 200;
+    pc.set(i, sorted[i]);
+  }
+  assert_eq!(pc.iter().collect::<Vec<_>>(), sorted);
+  for &v in sorted.iter().step_by(7) {
+    for v in [v.saturating_sub(1), v, v + 1] {
+      assert_eq!(pc.lower_bound(v), sorted.partition_point(|&x| x < v));
+    }
+  }
+}
+
+#[test]
+fn test_set_keeps_offsets() {
+  use jdb_pc::types::{BLOCK_LEN, BlockMode, PcConf};
+
+  let data: Vec<u64> = (0..20_000u64).map(|i| i * 500 + i * i % 251).collect();
+  for mode in [BlockMode::Fixed, BlockMode::Epsilon] {
+    let mut pc = Pc::new_with_conf(&data, PcConf::with_epsilon(8).with_mode(mode));
+    let offsets = |pc: &Pc| {
+      pc.block_meta
+        .iter()
+        .map(|m| (m.bit_offset, m.exception_offset))
+        .collect::<Vec<_>>()
+    };
+    let before = offsets(&pc);
+    let b = pc.block_count() / 2;
+    let start = match mode {
+      BlockMode::Fixed => b * BLOCK_LEN,
+      BlockMode::Epsilon => pc.starts[b] as usize,
+    };
+
+    // A new exception moves only later exception sections
+    // 新增异常值只后移之后的异常段
+    pc.set(start + 5, data[start + 5] + (1 << 50));
+    let after = offsets(&pc);
+    assert!(
+      before.iter().zip(&after).all(|(x, y)| x.0 == y.0),
+      "{mode:?}"
+    );
+    assert_eq!(before[..=b], after[..=b]);
+    let grow = after[b + 1].1 - before[b + 1].1;
+    assert!(grow > 0);
+    assert!(
+      before[b + 1..]
+        .iter()
+        .zip(&after[b + 1..])
+        .all(|(x, y)| y.1 - x.1 == grow)
+    );
+
+    // Re-encoding a block leaves the blocks before it untouched
+    // 重新编码某块时，其前的块保持不变
+    pc.set(start, data[start] + 3);
+    assert_eq!(offsets(&pc)[..b], before[..b], "{mode:?}");
+    assert_eq!(pc.get(start), Some(data[start] + 3));
+    assert_eq!(pc.get(start + 5), Some(data[start + 5] + (1 << 50)));
+  }
+}
+
+#[test]
+fn test_iter_both_ends() {
+  use jdb_pc::types::{BlockMode, PcConf};
+
+  let mut rng = StdRng::seed_from_u64(17);
+  let mut data: Vec<u64> = (0..12_345)
+    .map(|_| rng.random_range(0..1u64 << 44))
+    .collect();
+  data.sort_unstable();
+  data[777] = u64::MAX;
+
+  for mode in [BlockMode::Fixed, BlockMode::Epsilon] {
+    let pc = Pc::new_with_conf(&data, PcConf::with_epsilon(16).with_mode(mode));
+    assert_eq!(pc.iter().len(), data.len());
+    assert!(pc.iter().rev().eq(data.iter().rev().copied()));
+    assert!(pc.rev_iter().rev().eq(data.iter().copied()));
+    let (hi, lo) = (9, 3);
+    assert_eq!(pc.iter_range(hi..lo).len(), 0);
+    assert_eq!(pc.rev_iter_range(hi..lo).next(), None);
+
+    // Random mix of front and back pulls meets exactly once
+    // 前后随机交替取值，恰好相遇一次
+    for _ in 0..50 {
+      let a = rng.random_range(0..data.len());
+      let b = rng.random_range(a..=data.len());
+      let (mut it, mut rev) = (pc.iter_range(a..b), pc.rev_iter_range(a..b));
+      let (mut lo, mut hi) = (a, b);
+      while lo < hi {
+        assert_eq!(it.len(), hi - lo);
+        assert_eq!(rev.len(), hi - lo);
+        if rng.random() {
+          assert_eq!(it.next(), Some(data[lo]));
+          assert_eq!(rev.next_back(), Some(data[lo]));
+          lo += 1;
+        } else {
+          hi -= 1;
+          assert_eq!(it.next_back(), Some(data[hi]));
+          assert_eq!(rev.next(), Some(data[hi]));
+        }
+      }
+      assert_eq!((it.next(), it.next_back()), (None, None));
+      assert_eq!((rev.next(), rev.next_back()), (None, None));
+    }
+
+    // Seeking keeps going from the new position and never moves back
+    // 跳转后从新位置继续，且不会后退
+    let mut it = pc.iter();
+    assert_eq!(it.next(), Some(data[0]));
+    for target in [5, 6, 200, 199, 5_000, 5_001, 12_000] {
+      it.skip_to(target);
+      let at = target.max(data.len() - it.len());
+      assert_eq!(it.next(), Some(data[at]), "{target}");
+    }
+    it.skip_to(usize::MAX);
+    assert_eq!(it.next(), None);
+    assert_eq!(pc.iter().nth(4_321), Some(data[4_321]));
+    assert_eq!(pc.iter_range(100..200).nth(150), None);
+
+    let mut rev = pc.rev_iter();
+    for target in [12_000, 11_999, 12_300, 7_000, 6_999, 40] {
+      rev.skip_to(target);
+      let at = target.min(rev.len() - 1);
+      assert_eq!(rev.next(), Some(data[at]), "{target}");
+    }
+    rev.skip_to(0);
+    assert_eq!(rev.next(), Some(data[0]));
+    assert_eq!(rev.next(), None);
+    assert_eq!(pc.rev_iter().nth(10), Some(data[data.len() - 11]));
+  }
+}
+
+#[test]
+fn test_bulk_unpack() {
+  use jdb_pgm_lib::bits::{BitWriter, unpack, unpack_scalar};
+
+  let mut rng = StdRng::seed_from_u64(23);
+  for width in 0..=64u8 {
+    let mask = if width == 0 { 0 } else { !0u64 >> (64 - width) };
+    let lead = rng.random_range(0..64u8);
+    let vals: Vec<u64> = (0..203).map(|_| rng.random::<u64>() & mask).collect();
+    let mut w = BitWriter::with_capacity(0);
+    w.write(0, lead);
+    vals.iter().for_each(|&v| w.write(v, width));
+    let data = w.finish();
+
+    // Every start and length, so the scalar tail is hit at each alignment
+    // 覆盖各起点与长度，使标量尾部在每种对齐下都被触及
+    for from in [0, 1, 2, 3, 5, 64, 150] {
+      for n in [0, 1, 3, 4, 7, 8, 53] {
+        let start = lead as usize + from * width as usize;
+        let (mut fast, mut slow) = (vec![1; n], vec![1; n]);
+        unpack(&data, start, width, &mut fast);
+        unpack_scalar(&data, start, width, &mut slow);
+        assert_eq!(fast, &vals[from..from + n], "width {width} from {from}");
+        assert_eq!(fast, slow);
+      }
+    }
+  }
+}
+
+#[test]
+fn test_get_range_into() {
+  use jdb_pc::types::{BlockMode, PcConf};
+
+  let mut rng = StdRng::seed_from_u64(29);
+  let mut data: Vec<u64> = (0..9_000)
+    .map(|_| rng.random_range(0..1u64 << 40))
+    .collect();
+  data.sort_unstable();
+  for i in [0, 1_000, 1_001, 4_444, 8_999] {
+    data[i] = rng.random();
+  }
+
+  for conf in [
+    PcConf::default(),
+    PcConf::with_epsilon(8).with_mode(BlockMode::Fixed),
+    PcConf::default().with_large(),
+  ] {
+    let pc = Pc::new_with_conf(&data, conf);
+    let mut out = vec![0; data.len()];
+    pc.get_range_into(&mut out, 0..data.len());
+    assert_eq!(out, data);
+    for _ in 0..200 {
+      let a = rng.random_range(0..=data.len());
+      let b = rng.random_range(a..=data.len().min(a + 700));
+      let out = &mut out[..b - a];
+      pc.get_range_into(out, a..b);
+      assert_eq!(out, &data[a..b], "{a}..{b}");
+    }
+  }
+
+  let pc = Pc::new(&data, 8);
+  let res = std::panic::catch_unwind(|| pc.get_range_into(&mut [0; 2], 8_999..9_001));
+  assert!(res.is_err());
+}
+
+#[test]
+fn test_exception_sections() {
+  use jdb_pc::{PcView, io::HEAD_LEN, types::BlockMode, types::PcConf};
+
+  let mut rng = StdRng::seed_from_u64(31);
+  let mut cur = 0u64;
+  let data: Vec<u64> = (0..50_000)
+    .map(|_| {
+      cur += rng.random_range(0..64);
+      match rng.random_range(0..200) {
+        0 => rng.random(),
+        1 => cur + rng.random_range(0..1 << 20),
+        _ => cur,
+      }
+    })
+    .collect();
+
+  for mode in [BlockMode::Fixed, BlockMode::Epsilon] {
+    let pc = Pc::new_with_conf(&data, PcConf::default().with_mode(mode));
+    assert!(
+      (0..data.len()).all(|i| pc.get(i) == Some(data[i])),
+      "{mode:?}"
+    );
+
+    // Positions and per-block widths beat a bitmap plus 64-bit codes
+    // 位置加各块自有位宽，优于位图加 64 位码
+    let count = pc.exception_count();
+    assert!(count > 0, "{mode:?}");
+    assert!(pc.exceptions.len() * 8 < pc.len.div_ceil(64) * 8 + count * 8);
+    assert!(
+      pc.block_meta
+        .iter()
+        .any(|m| m.ex_count > 0 && m.ex_width < 64)
+    );
+
+    // A section count past the block is rejected without the CRC pass
+    // 越出块的异常个数即使跳过 CRC 也会被拒绝
+    let b_idx = pc.block_meta.iter().position(|m| m.ex_count > 0).unwrap();
+    let mut bytes = pc.dump();
+    let at = HEAD_LEN + b_idx * 32 + 28;
+    bytes[at..at + 2].copy_from_slice(&u16::MAX.to_le_bytes());
+    let w = aligned(&bytes);
+    assert!(PcView::new_skip_crc(as_bytes(&w, bytes.len())).is_err());
+  }
+}
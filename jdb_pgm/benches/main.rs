@@ -14,6 +14,11 @@ mod bench_common;
 mod bench_external_pgm;
 #[path = "parts/bench_jdb_pgm.rs"]
 mod bench_jdb_pgm;
+#[cfg(feature = "bench")]
+#[path = "parts/bench_set.rs"]
+mod bench_set;
+#[path = "parts/bench_sort.rs"]
+mod bench_sort;
 
 use std::time::Duration;
 
@@ -24,8 +29,12 @@ use bench_btreemap::BTreeMapIndex;
 use bench_common::{bench_build_impl, bench_query_impl, gen_queries, gen_seq};
 #[cfg(feature = "bench")]
 use bench_external_pgm::ExternalPgm;
-use bench_jdb_pgm::JdbPgm;
+use bench_jdb_pgm::{JdbPgm, JdbPgmEytzinger, JdbPgmSorted};
+#[cfg(feature = "bench")]
+use bench_set::bench_set_impl;
+use bench_sort::bench_sort_impl;
 use criterion::{Criterion, Throughput, criterion_group, criterion_main, measurement::WallTime};
+use rand::{Rng, SeedableRng, rngs::StdRng, seq::SliceRandom};
 
 #[global_allocator]
 static ALLOC: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
@@ -66,6 +75,23 @@ fn bench_single(c: &mut Criterion) {
   group.finish();
 }
 
+fn bench_layout(c: &mut Criterion) {
+  let mut group = setup_group(c, "segment_layout");
+
+  for &size in DATA_SIZES {
+    let data = gen_seq(size);
+    let queries = gen_queries(&data, 1000);
+    group.throughput(Throughput::Elements(queries.len() as u64));
+
+    for &eps in EPSILONS {
+      bench_query_impl::<JdbPgm>(&mut group, &data, &queries, size, Some(eps));
+      bench_query_impl::<JdbPgmSorted>(&mut group, &data, &queries, size, Some(eps));
+      bench_query_impl::<JdbPgmEytzinger>(&mut group, &data, &queries, size, Some(eps));
+    }
+  }
+  group.finish();
+}
+
 #[cfg(feature = "bench")]
 fn bench_batch(c: &mut Criterion) {
   let mut group = setup_group(c, "batch_lookups");
@@ -116,16 +142,49 @@ fn bench_compare(c: &mut Criterion) {
   group.finish();
 }
 
+fn bench_sort(c: &mut Criterion) {
+  let mut group = setup_group(c, "learned_sort");
+
+  for &size in DATA_SIZES {
+    let mut rng = StdRng::seed_from_u64(bench_common::SEED);
+    group.throughput(Throughput::Elements(size as u64));
+
+    let mut seq = gen_seq(size);
+    seq.shuffle(&mut rng);
+    bench_sort_impl(&mut group, "seq", &seq);
+
+    let uniform: Vec<u64> = (0..size).map(|_| rng.random()).collect();
+    bench_sort_impl(&mut group, "uniform", &uniform);
+  }
+  group.finish();
+}
+
+#[cfg(feature = "bench")]
+fn bench_set(c: &mut Criterion) {
+  let mut group = setup_group(c, "set_ops");
+
+  let data = gen_seq(1_000_000);
+
+  for small in [1_000, 10_000, 100_000, 1_000_000] {
+    group.throughput(Throughput::Elements(small as u64));
+    bench_set_impl(&mut group, &data, small);
+  }
+  group.finish();
+}
+
 #[cfg(feature = "bench")]
 criterion_group!(
   benches,
   bench_single,
   bench_batch,
   bench_build,
-  bench_compare
+  bench_compare,
+  bench_set,
+  bench_layout,
+  bench_sort
 );
 
 #[cfg(not(feature = "bench"))]
-criterion_group!(benches, bench_single, bench_build);
+criterion_group!(benches, bench_single, bench_build, bench_layout, bench_sort);
 
 criterion_main!(benches);
@@ -1,7 +1,7 @@
 //! jdb_pgm benchmark
 //! jdb_pgm 评测
 
-use jdb_pgm::Pgm;
+use jdb_pgm::{Layout, Pgm};
 
 use crate::bench_common::Benchmarkable;
 
@@ -9,45 +9,62 @@ use crate::bench_common::Benchmarkable;
 /// 线性扫描阈值
 const LINEAR_THRESHOLD: usize = 32;
 
-pub struct JdbPgm {
-  pgm: Pgm<u64>,
+/// Last-mile search in the predicted range
+/// 在预测范围内做最后一公里搜索
+#[inline]
+fn lookup(pgm: &Pgm<u64>, data: &[u64], key: u64) -> Option<usize> {
+  let range = pgm.predict_range(key);
+  let lo = range.start;
+  let hi = range.end;
+  let hi = hi.min(data.len());
+  let len = hi - lo;
+
+  if len <= LINEAR_THRESHOLD {
+    // Linear scan for small ranges
+    // 小范围线性扫描
+    for i in lo..hi {
+      let v = unsafe { *data.get_unchecked(i) };
+      if v == key {
+        return Some(i);
+      }
+      if v > key {
+        return None;
+      }
+    }
+    None
+  } else {
+    // Binary search for large ranges
+    // 大范围二分查找
+    unsafe { data.get_unchecked(lo..hi) }
+      .binary_search(&key)
+      .ok()
+      .map(|p| lo + p)
+  }
 }
 
-impl Benchmarkable for JdbPgm {
-  const NAME: &'static str = "jdb_pgm";
+/// Define a benchmark type for one segment search layout
+/// 为一种段搜索布局定义评测类型
+macro_rules! jdb_pgm_layout {
+  ($ty:ident, $name:literal, $layout:expr) => {
+    pub struct $ty {
+      pgm: Pgm<u64>,
+    }
 
-  fn build(data: &[u64], epsilon: Option<usize>) -> Self {
-    let pgm = Pgm::new(data, epsilon.unwrap_or(64));
-    Self { pgm }
-  }
+    impl Benchmarkable for $ty {
+      const NAME: &'static str = $name;
+
+      fn build(data: &[u64], epsilon: Option<usize>) -> Self {
+        let pgm = Pgm::new_with_layout(data, epsilon.unwrap_or(64), $layout);
+        Self { pgm }
+      }
 
-  fn query(&self, data: &[u64], key: u64) -> Option<usize> {
-    let range = self.pgm.predict_range(key);
-    let lo = range.start;
-    let hi = range.end;
-    let hi = hi.min(data.len());
-    let len = hi - lo;
-
-    if len <= LINEAR_THRESHOLD {
-      // Linear scan for small ranges
-      // 小范围线性扫描
-      for i in lo..hi {
-        let v = unsafe { *data.get_unchecked(i) };
-        if v == key {
-          return Some(i);
-        }
-        if v > key {
-          return None;
-        }
+      fn query(&self, data: &[u64], key: u64) -> Option<usize> {
+        lookup(&self.pgm, data, key)
       }
-      None
-    } else {
-      // Binary search for large ranges
-      // 大范围二分查找
-      unsafe { data.get_unchecked(lo..hi) }
-        .binary_search(&key)
-        .ok()
-        .map(|p| lo + p)
     }
-  }
+  };
 }
+
+jdb_pgm_layout!(JdbPgm, "jdb_pgm", Layout::Lut);
+jdb_pgm_layout!(JdbPgmSorted, "jdb_pgm_sorted", Layout::Sorted);
+jdb_pgm_layout!(JdbPgmEytzinger, "jdb_pgm_eytzinger", Layout::Eytzinger);
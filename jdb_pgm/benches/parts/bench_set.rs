@@ -0,0 +1,89 @@
+//! Set operation benchmark: learned skip vs plain merge
+//! 集合运算评测：学习型跳跃 vs 普通归并
+
+use std::hint::black_box;
+
+use criterion::{BenchmarkId, measurement::WallTime};
+use jdb_pgm::PgmData;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use crate::bench_common::SEED;
+
+/// Plain merge intersection
+/// 普通归并求交
+fn merge_intersect(a: &[u64], b: &[u64]) -> usize {
+  let (mut i, mut j, mut hits) = (0, 0, 0);
+  while i < a.len() && j < b.len() {
+    let (x, y) = (a[i], b[j]);
+    i += (x <= y) as usize;
+    j += (y <= x) as usize;
+    hits += (x == y) as usize;
+  }
+  hits
+}
+
+/// Plain merge union, counting emitted keys
+/// 普通归并求并集，统计输出的键数
+fn merge_union(a: &[u64], b: &[u64]) -> usize {
+  let (mut i, mut j, mut out) = (0, 0, 0);
+  while i < a.len() && j < b.len() {
+    let (x, y) = (a[i], b[j]);
+    i += (x <= y) as usize;
+    j += (y <= x) as usize;
+    out += 1;
+  }
+  out + (a.len() - i) + (b.len() - j)
+}
+
+/// Plain merge difference, counting keys of `a` absent from `b`
+/// 普通归并求差集，统计 `a` 中不在 `b` 内的键数
+fn merge_difference(a: &[u64], b: &[u64]) -> usize {
+  let (mut i, mut j, mut out) = (0, 0, 0);
+  while i < a.len() && j < b.len() {
+    let (x, y) = (a[i], b[j]);
+    i += (x <= y) as usize;
+    j += (y <= x) as usize;
+    out += (x < y) as usize;
+  }
+  out + (a.len() - i)
+}
+
+/// Pick `count` sorted keys from `data`
+/// 从 `data` 中抽取 `count` 个有序键
+fn sample(data: &[u64], count: usize) -> Vec<u64> {
+  let mut rng = StdRng::seed_from_u64(SEED + 1);
+  let mut v: Vec<u64> = (0..count)
+    .map(|_| data[rng.random_range(0..data.len())])
+    .collect();
+  v.sort_unstable();
+  v.dedup();
+  v
+}
+
+/// Benchmark intersect / union / difference against a plain merge
+/// 对比交集 / 并集 / 差集与普通归并
+pub fn bench_set_impl(group: &mut criterion::BenchmarkGroup<WallTime>, data: &[u64], small: usize) {
+  let small = sample(data, small);
+  let large = PgmData::new(data, 64);
+  let short = PgmData::new(&small, 64);
+  let n = small.len();
+
+  group.bench_with_input(BenchmarkId::new("merge_intersect", n), &n, |b, _| {
+    b.iter(|| black_box(merge_intersect(&small, data)))
+  });
+  group.bench_with_input(BenchmarkId::new("jdb_pgm_intersect", n), &n, |b, _| {
+    b.iter(|| black_box(short.intersect(&large).count()))
+  });
+  group.bench_with_input(BenchmarkId::new("merge_difference", n), &n, |b, _| {
+    b.iter(|| black_box(merge_difference(&small, data)))
+  });
+  group.bench_with_input(BenchmarkId::new("jdb_pgm_difference", n), &n, |b, _| {
+    b.iter(|| black_box(short.difference(&large).count()))
+  });
+  group.bench_with_input(BenchmarkId::new("merge_union", n), &n, |b, _| {
+    b.iter(|| black_box(merge_union(&small, data)))
+  });
+  group.bench_with_input(BenchmarkId::new("jdb_pgm_union", n), &n, |b, _| {
+    b.iter(|| black_box(short.union(&large).count()))
+  });
+}
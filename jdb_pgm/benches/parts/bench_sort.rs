@@ -0,0 +1,35 @@
+//! Learned sort benchmark vs sort_unstable
+//! 学习型排序与 sort_unstable 的评测
+
+use std::hint::black_box;
+
+use criterion::{BatchSize, BenchmarkId, measurement::WallTime};
+use jdb_pgm::sort::learned_sort;
+
+/// Benchmark sorting one unsorted input
+/// 对一份无序输入做排序评测
+pub fn bench_sort_impl(group: &mut criterion::BenchmarkGroup<WallTime>, dist: &str, data: &[u64]) {
+  let size = data.len();
+  group.bench_with_input(
+    BenchmarkId::new(format!("sort_unstable_{dist}"), size),
+    &data,
+    |b, data| {
+      b.iter_batched_ref(
+        || data.to_vec(),
+        |v| black_box(v).sort_unstable(),
+        BatchSize::LargeInput,
+      )
+    },
+  );
+  group.bench_with_input(
+    BenchmarkId::new(format!("learned_sort_{dist}"), size),
+    &data,
+    |b, data| {
+      b.iter_batched_ref(
+        || data.to_vec(),
+        |v| learned_sort(black_box(v)),
+        BatchSize::LargeInput,
+      )
+    },
+  );
+}
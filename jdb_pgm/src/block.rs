@@ -0,0 +1,236 @@
+//! SSTable block index: which data block may contain a key
+//! SSTable 块索引：键可能位于哪个数据块
+//!
+//! Built from the first key and file offset of every block. A `Pgm` over the
+//! first keys answers point lookups and scan ranges. The serialized form stores
+//! only keys and offsets, the model is rebuilt on `load`.
+//! 由每个块的首键与文件偏移构建。首键上的 `Pgm` 负责点查与扫描范围。
+//! 序列化形式只保存键和偏移，`load` 时重建模型。
+//!
+//! Layout (little endian) / 布局（小端）:
+//!
+//! | magic `PGMB` | version u8 | key size u8 | epsilon u32 | blocks u32 |
+//! | first keys | offsets u64 × (blocks + 1) |
+
+use alloc::{format, vec::Vec};
+use core::{
+  mem::size_of,
+  ops::{Bound, Range, RangeBounds},
+};
+
+use jdb_pgm_lib::error::{PgmError, Result};
+
+use crate::{Key, Pgm, types::KeyBytes};
+
+const MAGIC: [u8; 4] = *b"PGMB";
+const VERSION: u8 = 1;
+/// magic + version + key size + epsilon + blocks
+const HEAD_LEN: usize = 4 + 1 + 1 + 4 + 4;
+
+/// Index over the first keys of sorted, non-overlapping data blocks
+/// 有序且互不重叠的数据块首键索引
+#[derive(Clone, Debug)]
+pub struct BlockIndex<K: Key> {
+  pub pgm: Pgm<K>,
+  /// First key of each block, strictly increasing
+  /// 各块首键，严格递增
+  pub first_keys: Vec<K>,
+  /// Start offset of each block, followed by the end offset of the last one
+  /// 各块起始偏移，末尾追加最后一块的结束偏移
+  pub offsets: Vec<u64>,
+}
+
+impl<K: Key> BlockIndex<K> {
+  /// Build from `(first_key, offset)` of every block and the end offset of the
+  /// last block
+  /// 由各块的 `(首键, 偏移)` 及最后一块的结束偏移构建
+  pub fn new(blocks: &[(K, u64)], end: u64, epsilon: usize) -> Self {
+    let first_keys: Vec<K> = blocks.iter().map(|b| b.0).collect();
+    let mut offsets: Vec<u64> = blocks.iter().map(|b| b.1).collect();
+    offsets.push(end);
+    debug_assert!(first_keys.windows(2).all(|w| w[0] < w[1]));
+    debug_assert!(offsets.windows(2).all(|w| w[0] <= w[1]));
+    Self {
+      pgm: Pgm::new(&first_keys, epsilon),
+      first_keys,
+      offsets,
+    }
+  }
+
+  /// Number of blocks
+  /// 块数
+  #[inline]
+  #[must_use]
+  pub fn len(&self) -> usize {
+    self.first_keys.len()
+  }
+
+  /// Whether there are no blocks
+  /// 是否没有块
+  #[inline]
+  #[must_use]
+  pub fn is_empty(&self) -> bool {
+    self.first_keys.is_empty()
+  }
+
+  /// First block whose first key is >= `key`
+  /// 第一个首键 >= `key` 的块
+  #[inline]
+  fn lower_bound(&self, key: K) -> usize {
+    // SAFETY: Pgm::find_key callback is only called with indices within bounds
+    self
+      .pgm
+      .find_key(key, |i| unsafe { Some(*self.first_keys.get_unchecked(i)) })
+  }
+
+  /// First block whose first key is > `key`
+  /// 第一个首键 > `key` 的块
+  #[inline]
+  fn upper_bound(&self, key: K) -> usize {
+    let pos = self.lower_bound(key);
+    pos + (self.first_keys.get(pos) == Some(&key)) as usize
+  }
+
+  /// `(offset, len)` of block `no`
+  /// 块 `no` 的 `(偏移, 长度)`
+  #[inline]
+  #[must_use]
+  pub fn block(&self, no: usize) -> (u64, u64) {
+    let start = self.offsets[no];
+    (start, self.offsets[no + 1] - start)
+  }
+
+  /// The block that may contain `key` as `(block_no, offset, len)`
+  /// 可能包含 `key` 的块，返回 `(块号, 偏移, 长度)`
+  ///
+  /// None if `key` is below the first key of the first block.
+  /// 若 `key` 小于第一个块的首键则返回 None。
+  #[must_use]
+  pub fn find_block(&self, key: K) -> Option<(usize, u64, u64)> {
+    let no = self.upper_bound(key).checked_sub(1)?;
+    let (offset, len) = self.block(no);
+    Some((no, offset, len))
+  }
+
+  /// Blocks that may hold keys in `range`, for scans
+  /// 可能包含 `range` 内键的块，用于扫描
+  #[must_use]
+  pub fn block_range<R: RangeBounds<K>>(&self, range: R) -> Range<usize> {
+    let start = match range.start_bound() {
+      Bound::Included(&k) | Bound::Excluded(&k) => self.upper_bound(k).saturating_sub(1),
+      Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+      Bound::Included(&k) => self.upper_bound(k),
+      Bound::Excluded(&k) => self.lower_bound(k),
+      Bound::Unbounded => self.len(),
+    };
+    start..end.max(start)
+  }
+
+  /// File byte range covering `blocks` (one contiguous read)
+  /// 覆盖 `blocks` 的文件字节范围（一次连续读取）
+  #[inline]
+  #[must_use]
+  pub fn byte_range(&self, blocks: Range<usize>) -> Range<u64> {
+    self.offsets[blocks.start]..self.offsets[blocks.end]
+  }
+
+  /// Memory usage in bytes
+  /// 内存占用字节数
+  #[inline]
+  #[must_use]
+  pub fn mem_usage(&self) -> usize {
+    self.pgm.mem_usage()
+      + self.first_keys.len() * size_of::<K>()
+      + self.offsets.len() * size_of::<u64>()
+  }
+}
+
+impl<K: KeyBytes> BlockIndex<K> {
+  /// Serialize for a table footer
+  /// 序列化以存入表尾
+  ///
+  /// # Panics
+  /// If epsilon or the block count does not fit the footer's u32 fields.
+  /// 若 epsilon 或块数放不进表尾的 u32 字段。
+  #[must_use]
+  pub fn dump(&self) -> Vec<u8> {
+    let n = self.len();
+    let epsilon = u32::try_from(self.pgm.epsilon).expect("BlockIndex epsilon exceeds u32");
+    let blocks = u32::try_from(n).expect("BlockIndex block count exceeds u32");
+    let mut out = Vec::with_capacity(HEAD_LEN + n * K::SIZE + (n + 1) * 8);
+    out.extend_from_slice(&MAGIC);
+    out.push(VERSION);
+    out.push(K::SIZE as u8);
+    out.extend_from_slice(&epsilon.to_le_bytes());
+    out.extend_from_slice(&blocks.to_le_bytes());
+    for &k in &self.first_keys {
+      k.put_le(&mut out);
+    }
+    for &o in &self.offsets {
+      out.extend_from_slice(&o.to_le_bytes());
+    }
+    out
+  }
+
+  /// Deserialize from `dump` output
+  /// 从 `dump` 的输出反序列化
+  pub fn load(bytes: &[u8]) -> Result<Self> {
+    let invalid = |msg: &str| PgmError::InvalidData(format!("BlockIndex: {msg}"));
+    if bytes.len() < HEAD_LEN {
+      return Err(invalid("too short for header"));
+    }
+    if bytes[..4] != MAGIC {
+      return Err(invalid("bad magic"));
+    }
+    if bytes[4] != VERSION {
+      return Err(invalid(&format!("unsupported version {}", bytes[4])));
+    }
+    if bytes[5] as usize != K::SIZE {
+      return Err(invalid(&format!("key size {} != {}", bytes[5], K::SIZE)));
+    }
+    let u32_at =
+      |p: usize| u32::from_le_bytes([bytes[p], bytes[p + 1], bytes[p + 2], bytes[p + 3]]);
+    let epsilon = u32_at(6) as usize;
+    let n = u32_at(10) as usize;
+    // Checked, as usize may be 32 bits
+    // 需检查溢出，usize 可能只有 32 位
+    let keys_end = n.checked_mul(K::SIZE).and_then(|k| k.checked_add(HEAD_LEN));
+    let total = n
+      .checked_add(1)
+      .and_then(|o| o.checked_mul(8))
+      .zip(keys_end)
+      .and_then(|(o, k)| o.checked_add(k));
+    let (Some(keys_end), Some(total)) = (keys_end, total) else {
+      return Err(invalid(&format!("{n} blocks overflow usize")));
+    };
+    if bytes.len() != total {
+      return Err(invalid(&format!("length {} for {n} blocks", bytes.len())));
+    }
+
+    let first_keys: Vec<K> = bytes[HEAD_LEN..keys_end]
+      .chunks_exact(K::SIZE)
+      .map(K::get_le)
+      .collect();
+    let offsets: Vec<u64> = bytes[keys_end..]
+      .chunks_exact(8)
+      .map(|c| {
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(c);
+        u64::from_le_bytes(buf)
+      })
+      .collect();
+    if !first_keys.windows(2).all(|w| w[0] < w[1]) {
+      return Err(invalid("first keys not strictly increasing"));
+    }
+    if !offsets.windows(2).all(|w| w[0] <= w[1]) {
+      return Err(invalid("offsets not sorted"));
+    }
+    Ok(Self {
+      pgm: Pgm::new(&first_keys, epsilon),
+      first_keys,
+      offsets,
+    })
+  }
+}
@@ -5,14 +5,27 @@
 
 #![allow(clippy::cast_precision_loss)]
 
+use alloc::{vec, vec::Vec};
+
 use super::{
   consts::{LUT_BINS_MULTIPLIER, MAX_LUT_BINS, MIN_LUT_BINS},
-  types::{Key, Segment},
+  predict_in_seg,
+  types::{Key, Model, Segment},
 };
 
 /// Build segments using the streaming shrinking cone algorithm.
 /// 使用流式收缩锥算法构建段 (O(N))
 pub fn build_segments<K: Key>(sorted: &[K], epsilon: usize) -> Vec<Segment<K>> {
+  build_segments_by(sorted, |&k| k, epsilon)
+}
+
+/// Build segments over items sorted by an extracted key
+/// 在按提取键排序的元素上构建段
+pub fn build_segments_by<T, K: Key>(
+  sorted: &[T],
+  key: impl Fn(&T) -> K,
+  epsilon: usize,
+) -> Vec<Segment<K>> {
   let n = sorted.len();
   if n == 0 {
     return vec![];
@@ -27,7 +40,7 @@ pub fn build_segments<K: Key>(sorted: &[K], epsilon: usize) -> Vec<Segment<K>> {
 
   while start < n {
     // SAFETY: start is less than n, so ptr.add(start) is within bounds.
-    let first_key = unsafe { (*ptr.add(start)).as_f64() };
+    let first_key = key(unsafe { &*ptr.add(start) }).as_f64();
     let first_idx = start as f64;
 
     let mut min_slope = f64::NEG_INFINITY;
@@ -37,9 +50,9 @@ pub fn build_segments<K: Key>(sorted: &[K], epsilon: usize) -> Vec<Segment<K>> {
 
     while end < n {
       // SAFETY: end is checked < n, so ptr.add(end) is valid.
-      let key = unsafe { (*ptr.add(end)).as_f64() };
+      let k = key(unsafe { &*ptr.add(end) }).as_f64();
       let idx = end as f64;
-      let dx = key - first_key;
+      let dx = k - first_key;
 
       if dx == 0.0 {
         if (idx - first_idx) > (2 * epsilon) as f64 {
@@ -72,15 +85,31 @@ pub fn build_segments<K: Key>(sorted: &[K], epsilon: usize) -> Vec<Segment<K>> {
 
     let intercept = first_idx - slope * first_key;
 
-    segments.push(Segment {
+    let mut seg = Segment {
       // SAFETY: indices are within bounds [0, n).
-      min_key: unsafe { *sorted.get_unchecked(start) },
-      max_key: unsafe { *sorted.get_unchecked(end - 1) },
+      min_key: key(unsafe { sorted.get_unchecked(start) }),
+      max_key: key(unsafe { sorted.get_unchecked(end - 1) }),
       slope,
       intercept,
       start_idx: start,
       end_idx: end,
-    });
+      err_lo: 0,
+      err_hi: 0,
+    };
+
+    // Record the real error on each side, usually well below epsilon
+    // 记录两侧的实际误差，通常远小于 epsilon
+    let (mut err_lo, mut err_hi) = (0, 0);
+    let model = Model::from(&seg);
+    for i in start..end {
+      // SAFETY: start..end lies within [0, n).
+      let pred = predict_in_seg(&model, key(unsafe { &*ptr.add(i) }).as_f64());
+      err_lo = err_lo.max(pred.saturating_sub(i));
+      err_hi = err_hi.max(i.saturating_sub(pred));
+    }
+    seg.err_lo = u32::try_from(err_lo).unwrap_or(u32::MAX);
+    seg.err_hi = u32::try_from(err_hi).unwrap_or(u32::MAX);
+    segments.push(seg);
 
     start = end;
   }
@@ -91,15 +120,23 @@ pub fn build_segments<K: Key>(sorted: &[K], epsilon: usize) -> Vec<Segment<K>> {
 /// Build lookup table for fast segment search
 /// 构建查找表以快速搜索段
 pub fn build_lut<K: Key>(sorted: &[K], segments: &[Segment<K>]) -> (Vec<u32>, f64, f64) {
-  if sorted.is_empty() || segments.is_empty() {
+  if sorted.is_empty() {
     return (vec![0], 0.0, 0.0);
   }
+  build_lut_by_segments(segments)
+}
+
+/// Build lookup table from segments alone (their key span equals the data's)
+/// 仅由段构建查找表（段的键跨度与数据一致）
+pub(crate) fn build_lut_by_segments<K: Key>(segments: &[Segment<K>]) -> (Vec<u32>, f64, f64) {
+  let (Some(first), Some(last)) = (segments.first(), segments.last()) else {
+    return (vec![0], 0.0, 0.0);
+  };
 
   let bins = (segments.len() * LUT_BINS_MULTIPLIER).clamp(MIN_LUT_BINS, MAX_LUT_BINS);
 
-  // SAFETY: checked emptiness above.
-  let min_key = unsafe { sorted.get_unchecked(0) }.as_f64();
-  let max_key = unsafe { sorted.get_unchecked(sorted.len() - 1) }.as_f64();
+  let min_key = first.min_key.as_f64();
+  let max_key = last.max_key.as_f64();
 
   let span = (max_key - min_key).max(1.0);
   let scale = bins as f64 / span;
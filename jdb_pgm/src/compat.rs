@@ -0,0 +1,85 @@
+//! Decoding of bitcode written before per-segment error windows
+//! 解码引入段误差窗口之前写入的 bitcode
+//!
+//! The first released layout has no `err_lo` / `err_hi` and no search layout.
+//! Such a model is loaded with the build epsilon as its error window on both
+//! sides, which is exactly the window it was searched with.
+//! 首个发布的布局没有 `err_lo` / `err_hi`，也没有搜索布局。加载时以构建
+//! epsilon 作为两侧误差窗口，与其原本的查找窗口一致。
+
+use alloc::vec::Vec;
+
+use crate::{Layout, Pgm, types::Key, types::Segment};
+
+/// Segment in the first released encoding
+/// 首个发布编码中的段
+#[derive(bitcode::Encode, bitcode::Decode, Clone, Copy, Debug)]
+pub struct SegmentV0<K: Key> {
+  pub min_key: K,
+  pub max_key: K,
+  pub slope: f64,
+  pub intercept: f64,
+  pub start_idx: usize,
+  pub end_idx: usize,
+}
+
+/// Pgm in the first released encoding
+/// 首个发布编码中的 Pgm
+#[derive(bitcode::Encode, bitcode::Decode, Clone, Debug)]
+pub struct PgmV0<K: Key> {
+  pub epsilon: usize,
+  pub segments: Vec<SegmentV0<K>>,
+  pub lut: Vec<u32>,
+  pub scale: f64,
+  pub min_key: f64,
+  pub len: usize,
+}
+
+impl<K: Key> From<PgmV0<K>> for Pgm<K> {
+  fn from(old: PgmV0<K>) -> Self {
+    let err = u32::try_from(old.epsilon).unwrap_or(u32::MAX);
+    let segments = old
+      .segments
+      .into_iter()
+      .map(|s| Segment {
+        min_key: s.min_key,
+        max_key: s.max_key,
+        slope: s.slope,
+        intercept: s.intercept,
+        start_idx: s.start_idx,
+        end_idx: s.end_idx,
+        err_lo: err,
+        err_hi: err,
+      })
+      .collect();
+    Self {
+      epsilon: old.epsilon,
+      segments,
+      lut: old.lut,
+      scale: old.scale,
+      min_key: old.min_key,
+      len: old.len,
+      layout: Layout::Lut,
+      max_keys: Vec::new(),
+      ranks: Vec::new(),
+      min_keys: Vec::new(),
+      models: Vec::new(),
+    }
+  }
+}
+
+impl<K: Key> Pgm<K>
+where
+  Self: bitcode::DecodeOwned,
+  PgmV0<K>: bitcode::DecodeOwned,
+{
+  /// Decode bitcode of the current encoding, falling back to the first released one
+  /// 解码当前编码的 bitcode，失败时回退到首个发布的编码
+  pub fn decode_bitcode(bytes: &[u8]) -> Result<Self, bitcode::Error> {
+    bitcode::decode::<Self>(bytes).or_else(|e| {
+      bitcode::decode::<PgmV0<K>>(bytes)
+        .map(Self::from)
+        .map_err(|_| e)
+    })
+  }
+}
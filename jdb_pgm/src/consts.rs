@@ -5,3 +5,19 @@ pub const MIN_EPSILON: usize = 1;
 pub const MIN_LUT_BINS: usize = 1024;
 pub const MAX_LUT_BINS: usize = 1 << 20;
 pub const LUT_BINS_MULTIPLIER: usize = 4;
+
+/// Size ratio above which set operations switch from merge to learned skip
+/// 集合运算从归并切换为学习型跳跃的长度比阈值
+pub const SKIP_RATIO: usize = 8;
+
+/// Below this length learned_sort falls back to sort_unstable
+/// 低于该长度时 learned_sort 回退到 sort_unstable
+pub const SORT_MIN_LEN: usize = 1 << 12;
+
+/// Target keys per bucket in learned_sort
+/// learned_sort 中每个桶的目标键数
+pub const SORT_BUCKET_LEN: usize = 32;
+
+/// Z-range holding at most this many keys is scanned instead of split further
+/// 键数不超过该值的 Z 区间直接扫描，不再继续拆分
+pub const ZORDER_SCAN_LEN: usize = 64;
@@ -1,20 +1,30 @@
-//! Pgm-Index with data ownership
-//! 持有数据的 Pgm 索引
+//! Pgm-Index over sorted key storage
+//! 基于有序键存储的 Pgm 索引
+//!
+//! Storage is any `S: AsRef<[K]>`: an owned `Vec<K>` (the default), a borrowed
+//! slice (`PgmRef`), an `Arc<[K]>`, a memory map or a column buffer.
+//! 存储可以是任意 `S: AsRef<[K]>`：自有的 `Vec<K>`（默认）、借用切片
+//! （`PgmRef`）、`Arc<[K]>`、内存映射或列缓冲区。
 
-use std::{mem::size_of, ops::Deref};
+use alloc::vec::Vec;
+use core::{mem::size_of_val, ops::Deref};
 
 use crate::{Key, Pgm};
 
-/// Pgm-Index with data ownership
-/// 持有数据的 Pgm 索引
+/// Pgm-Index over sorted key storage (owns a `Vec<K>` by default)
+/// 基于有序键存储的 Pgm 索引（默认持有 `Vec<K>`）
 #[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
 #[derive(Clone, Debug)]
-pub struct PgmData<K: Key> {
+pub struct PgmData<K: Key, S: AsRef<[K]> = Vec<K>> {
   pub pgm: Pgm<K>,
-  pub sorted: Vec<K>,
+  pub sorted: S,
 }
 
-impl<K: Key> Deref for PgmData<K> {
+/// Pgm-Index borrowing its keys
+/// 借用键数据的 Pgm 索引
+pub type PgmRef<'a, K> = PgmData<K, &'a [K]>;
+
+impl<K: Key, S: AsRef<[K]>> Deref for PgmData<K, S> {
   type Target = Pgm<K>;
 
   #[inline]
@@ -24,22 +34,45 @@ impl<K: Key> Deref for PgmData<K> {
 }
 
 impl<K: Key> PgmData<K> {
-  /// Create Pgm-Index from sorted data slice
-  /// 从已排序数据切片创建 Pgm 索引
+  /// Create Pgm-Index from sorted data slice (copies the keys)
+  /// 从已排序数据切片创建 Pgm 索引（复制键）
   pub fn new(sorted: &[K], epsilon: usize) -> Self {
-    let pgm = Pgm::new(sorted, epsilon);
+    Self::from_storage(sorted.to_vec(), epsilon)
+  }
+}
+
+impl<'a, K: Key> PgmRef<'a, K> {
+  /// Index a sorted slice in place, without copying
+  /// 原地索引有序切片，不复制
+  pub fn borrowed(sorted: &'a [K], epsilon: usize) -> Self {
+    Self::from_storage(sorted, epsilon)
+  }
+}
+
+impl<K: Key, S: AsRef<[K]>> PgmData<K, S> {
+  /// Index existing sorted storage without copying it
+  /// 索引已有的有序存储，不复制
+  pub fn from_storage(sorted: S, epsilon: usize) -> Self {
     Self {
-      pgm,
-      sorted: sorted.to_vec(),
+      pgm: Pgm::new(sorted.as_ref(), epsilon),
+      sorted,
     }
   }
 
+  /// Give back the storage
+  /// 交还存储
+  #[inline]
+  #[must_use]
+  pub fn into_storage(self) -> S {
+    self.sorted
+  }
+
   /// Get reference to underlying data
   /// 获取底层数据引用
   #[inline]
   #[must_use]
   pub fn sorted(&self) -> &[K] {
-    &self.sorted
+    self.sorted.as_ref()
   }
 
   /// Get position of key (None if absent)
@@ -47,13 +80,14 @@ impl<K: Key> PgmData<K> {
   #[inline]
   #[must_use]
   pub fn get(&self, key: K) -> Option<usize> {
+    let sorted = self.sorted();
     // SAFETY: Pgm::find_key callback is only called with indices within data bounds
     // (guaranteed by Pgm::predict_range).
     let idx = self
       .pgm
-      .find_key(key, |i| unsafe { Some(*self.sorted.get_unchecked(i)) });
+      .find_key(key, |i| unsafe { Some(*sorted.get_unchecked(i)) });
     // SAFETY: idx checked against len() before access
-    if idx < self.sorted.len() && unsafe { *self.sorted.get_unchecked(idx) } == key {
+    if idx < sorted.len() && unsafe { *sorted.get_unchecked(idx) } == key {
       Some(idx)
     } else {
       None
@@ -81,12 +115,12 @@ impl<K: Key> PgmData<K> {
     keys.into_iter().filter(|&k| self.get(k).is_some()).count()
   }
 
-  /// Memory usage (including data)
-  /// 内存占用（含数据）
+  /// Memory usage (including data, even when borrowed)
+  /// 内存占用（含数据，借用时同样计入）
   #[inline]
   #[must_use]
   pub fn memory_usage(&self) -> usize {
-    self.sorted.len() * size_of::<K>() + self.pgm.mem_usage()
+    size_of_val(self.sorted()) + self.pgm.mem_usage()
   }
 
   /// Get predicted position for a key (for benchmarking)
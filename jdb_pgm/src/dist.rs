@@ -0,0 +1,69 @@
+//! CDF / quantile sketch API over the Pgm model
+//! 基于 Pgm 模型的 CDF / 分位数概要 API
+
+use alloc::vec::Vec;
+
+use crate::{Key, KeyFromF64, Pgm, mul_add};
+
+impl<K: Key> Pgm<K> {
+  /// Normalized rank estimate: fraction of keys `< key`, in [0, 1]
+  /// 归一化秩估计：小于 `key` 的键所占比例，范围 [0, 1]
+  #[must_use]
+  pub fn cdf(&self, key: K) -> f64 {
+    if self.segment_count() == 0 {
+      return 0.0;
+    }
+    let seg = self.segment(self.find_seg_idx(key));
+    // At `min_key` the rank is the segment start, which also covers segments
+    // of one repeated key whose slope is not finite
+    // 在 `min_key` 处秩即段起点，这也覆盖了只含一个重复键、斜率非有限的段
+    let pos = if key <= seg.min_key {
+      seg.start_idx as f64
+    } else if key > seg.max_key {
+      seg.end_idx as f64
+    } else {
+      mul_add(seg.slope, key.as_f64(), seg.intercept)
+        .clamp(seg.start_idx as f64, seg.end_idx as f64)
+    };
+    pos / self.len as f64
+  }
+}
+
+impl<K: KeyFromF64> Pgm<K> {
+  /// Key at normalized rank `p` (clamped to [0, 1]), None if empty
+  /// 归一化秩 `p`（截断到 [0, 1]）处的键，为空时返回 None
+  ///
+  /// Solves the segment model for the position and clamps to the segment's key range.
+  /// 求解段模型得到位置，并截断到该段的键范围。
+  #[must_use]
+  pub fn quantile(&self, p: f64) -> Option<K> {
+    if self.len == 0 {
+      return None;
+    }
+    let p = if p.is_nan() { 0.0 } else { p.clamp(0.0, 1.0) };
+    let pos = (p * self.len as f64) as usize;
+    let pos = pos.min(self.len - 1);
+    let seg = self.find_segment_for_index(pos)?;
+    if seg.slope <= 0.0 {
+      return Some(seg.min_key);
+    }
+    let x = (pos as f64 - seg.intercept) / seg.slope;
+    Some(K::from_f64(x).clamp(seg.min_key, seg.max_key))
+  }
+
+  /// Boundaries splitting the keys into `n_parts` balanced ranges
+  /// 将键划分为 `n_parts` 个均衡范围的边界
+  ///
+  /// Returns up to `n_parts - 1` ascending, deduplicated keys; part `i` is
+  /// `[b[i-1], b[i])` with open ends at both sides.
+  /// 返回至多 `n_parts - 1` 个升序去重的键；第 `i` 段为 `[b[i-1], b[i])`，
+  /// 两端开放。
+  #[must_use]
+  pub fn partition_points(&self, n_parts: usize) -> Vec<K> {
+    let mut out: Vec<K> = (1..n_parts)
+      .filter_map(|i| self.quantile(i as f64 / n_parts as f64))
+      .collect();
+    out.dedup();
+    out
+  }
+}
@@ -0,0 +1,82 @@
+//! Approximate range counting from the Pgm model (no data access)
+//! 基于 Pgm 模型的近似范围计数（不访问数据）
+
+use core::ops::{Bound, Range, RangeBounds};
+
+use crate::{
+  Key, Pgm, predict_in_seg, seg_window,
+  types::{CountEstimate, Model},
+};
+
+/// Rank estimate with bounds: (estimate, lo, hi)
+/// 带边界的秩估计：(估计值, 下界, 上界)
+type Rank = (usize, usize, usize);
+
+impl<K: Key> Pgm<K> {
+  /// Estimate how many keys fall in `range`, with guaranteed lower and upper bounds
+  /// 估计落在 `range` 内的键数量，并给出保证成立的上下界
+  ///
+  /// On distinct keys `upper - lower` is at most `4ε + 2`; duplicate runs may
+  /// widen it. It is exact when both ends fall outside the key range or in gaps
+  /// between segments.
+  /// 键互不相同时 `upper - lower` 至多为 `4ε + 2`，重复键可能使其变宽；
+  /// 两端都落在键范围之外或段间空隙时结果精确。
+  #[must_use]
+  pub fn estimate_count<R: RangeBounds<K>>(&self, range: R) -> CountEstimate {
+    let start = match range.start_bound() {
+      Bound::Included(&k) => self.rank(k, false),
+      Bound::Excluded(&k) => self.rank(k, true),
+      Bound::Unbounded => (0, 0, 0),
+    };
+    let end = match range.end_bound() {
+      Bound::Included(&k) => self.rank(k, true),
+      Bound::Excluded(&k) => self.rank(k, false),
+      Bound::Unbounded => (self.len, self.len, self.len),
+    };
+    let lower = end.1.saturating_sub(start.2);
+    let upper = end.2.saturating_sub(start.1);
+    CountEstimate {
+      estimate: end.0.saturating_sub(start.0).clamp(lower, upper),
+      lower,
+      upper,
+    }
+  }
+
+  /// Bounds on the number of keys `< key` (or `<= key` when `inclusive`)
+  /// 小于 `key`（`inclusive` 时为小于等于）的键数量的边界
+  ///
+  /// A duplicate run may span several segments sharing its key, so the rank
+  /// is bounded in the first such segment (`<`) or the last one (`<=`).
+  /// 重复键可能跨越多个共享该键的段，因此秩在第一个（`<`）或最后一个（`<=`）
+  /// 这样的段内界定。
+  fn rank(&self, key: K, inclusive: bool) -> Rank {
+    let n = self.segment_count();
+    if n == 0 {
+      return (0, 0, 0);
+    }
+    let mut idx = self.find_seg_idx(key);
+    if inclusive {
+      while idx + 1 < n && self.segment(idx + 1).min_key <= key {
+        idx += 1;
+      }
+    } else {
+      while idx > 0 && self.segment(idx - 1).max_key >= key {
+        idx -= 1;
+      }
+    }
+    let seg = &self.segment(idx);
+    if key < seg.min_key {
+      let r = seg.start_idx;
+      return (r, r, r);
+    }
+    if key > seg.max_key {
+      let r = seg.end_idx;
+      return (r, r, r);
+    }
+    let m = Model::from(seg);
+    let pred = predict_in_seg(&m, key.as_f64());
+    let Range { start: lo, end: hi } = seg_window(&m, pred);
+    let est = if inclusive { pred + 1 } else { pred };
+    (est.clamp(lo, hi), lo, hi)
+  }
+}
@@ -0,0 +1,149 @@
+//! Segment search layouts
+//! 段搜索布局
+//!
+//! `Lut` walks `Segment` structs from a look-up table bin. `Sorted` and
+//! `Eytzinger` keep no `Segment` structs: they search a dense `max_keys`
+//! array, so each search step touches only keys, and read the dense `models`
+//! array once at the end. Segment min keys sit in their own array, read only
+//! by `cdf`, `quantile` and `estimate_count`.
+//! `Lut` 从查找表桶出发遍历 `Segment` 结构体。`Sorted` 与 `Eytzinger`
+//! 不保存 `Segment` 结构体：搜索紧凑的 `max_keys` 数组，每步只访问键，最后只读取
+//! 一次紧凑的 `models` 数组。段最小键单独成数组，仅供 `cdf`、`quantile` 与
+//! `estimate_count` 读取。
+
+use alloc::{vec, vec::Vec};
+use core::cmp::Ordering;
+
+use crate::types::{Key, Model, Segment};
+
+/// Segment search layout (build option)
+/// 段搜索布局（构建选项）
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Layout {
+  /// Look-up table + linear walk over segments
+  /// 查找表 + 段上线性遍历
+  #[default]
+  Lut,
+  /// Binary search over dense sorted segment max keys
+  /// 在紧凑有序的段最大键数组上二分查找
+  Sorted,
+  /// Branchless search over max keys in Eytzinger (BFS) order
+  /// 在 Eytzinger（层序）排列的最大键上无分支查找
+  Eytzinger,
+}
+
+/// Build the max-key search array and its rank map for a layout
+/// 为布局构建最大键搜索数组及其秩映射
+///
+/// `Sorted`: keys in order, no ranks. `Eytzinger`: 1-based BFS order, `ranks[k]`
+/// is the sorted position of `keys[k]`.
+/// `Sorted`：按序存放，无秩映射。`Eytzinger`：从 1 开始的层序，`ranks[k]`
+/// 为 `keys[k]` 的有序位置。
+pub fn build_max_keys<K: Key>(segments: &[Segment<K>], layout: Layout) -> (Vec<K>, Vec<u32>) {
+  match layout {
+    Layout::Lut => (vec![], vec![]),
+    Layout::Sorted => (segments.iter().map(|s| s.max_key).collect(), vec![]),
+    Layout::Eytzinger => {
+      let Some(first) = segments.first() else {
+        return (vec![], vec![]);
+      };
+      let n = segments.len();
+      let mut keys = vec![first.max_key; n + 1];
+      let mut ranks = vec![0u32; n + 1];
+      let mut i = 0;
+      fill_eytzinger(segments, &mut keys, &mut ranks, &mut i, 1);
+      (keys, ranks)
+    }
+  }
+}
+
+fn fill_eytzinger<K: Key>(
+  segments: &[Segment<K>],
+  keys: &mut [K],
+  ranks: &mut [u32],
+  i: &mut usize,
+  k: usize,
+) {
+  if k <= segments.len() {
+    fill_eytzinger(segments, keys, ranks, i, 2 * k);
+    keys[k] = segments[*i].max_key;
+    ranks[k] = *i as u32;
+    *i += 1;
+    fill_eytzinger(segments, keys, ranks, i, 2 * k + 1);
+  }
+}
+
+/// Dense min-key and model arrays for a layout (empty for `Lut`, which reads
+/// `Segment`)
+/// 布局对应的紧凑最小键与模型数组（`Lut` 直接读取 `Segment`，为空）
+pub fn build_models<K: Key>(segments: &[Segment<K>], layout: Layout) -> (Vec<K>, Vec<Model>) {
+  match layout {
+    Layout::Lut => (vec![], vec![]),
+    Layout::Sorted | Layout::Eytzinger => (
+      segments.iter().map(|s| s.min_key).collect(),
+      segments.iter().map(Model::from).collect(),
+    ),
+  }
+}
+
+/// Slot of the `rank`-th key in an Eytzinger array of `n` keys
+/// 含 `n` 个键的 Eytzinger 数组中第 `rank` 个键的槽位
+pub(crate) fn eytzinger_slot(n: usize, mut rank: usize) -> usize {
+  let mut k = 1;
+  loop {
+    let left = subtree_len(n, 2 * k);
+    match rank.cmp(&left) {
+      Ordering::Less => k *= 2,
+      Ordering::Equal => return k,
+      Ordering::Greater => {
+        rank -= left + 1;
+        k = 2 * k + 1;
+      }
+    }
+  }
+}
+
+/// Number of nodes under slot `k` (itself included) in a tree of `n` slots
+/// 含 `n` 个槽位的树中槽位 `k` 之下（含自身）的节点数
+fn subtree_len(n: usize, k: usize) -> usize {
+  let (mut lo, mut hi, mut len) = (k, k, 0);
+  while lo <= n {
+    len += hi.min(n) - lo + 1;
+    lo *= 2;
+    hi = 2 * hi + 1;
+  }
+  len
+}
+
+/// Index of the first segment whose max key >= key (last segment if none)
+/// 第一个最大键 >= key 的段下标（不存在则为最后一段）
+///
+/// Duplicate runs may span segments sharing the same keys, so the first such
+/// segment is the one holding the lower bound, as in the `Lut` walk.
+/// 重复键可能跨越多个键相同的段，第一个满足条件的段才包含下界，与 `Lut` 遍历一致。
+#[inline]
+pub(crate) fn search_sorted<K: Key>(keys: &[K], key: K) -> usize {
+  keys.partition_point(|&k| k < key).min(keys.len() - 1)
+}
+
+/// Same as `search_sorted` over an Eytzinger array
+/// 与 `search_sorted` 相同，但作用于 Eytzinger 数组
+#[inline]
+pub(crate) fn search_eytzinger<K: Key>(keys: &[K], ranks: &[u32], key: K) -> usize {
+  let n = keys.len() - 1;
+  let mut k = 1;
+  while k <= n {
+    // SAFETY: 1 <= k <= n < keys.len()
+    k = 2 * k + (unsafe { *keys.get_unchecked(k) } < key) as usize;
+  }
+  // Drop the trailing right turns to reach the first key >= `key`
+  // 去掉末尾的右转，得到第一个 >= `key` 的位置
+  k >>= k.trailing_ones() + 1;
+  if k == 0 {
+    n - 1
+  } else {
+    // SAFETY: 1 <= k <= n
+    unsafe { *ranks.get_unchecked(k) as usize }
+  }
+}
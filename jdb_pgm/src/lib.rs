@@ -1,22 +1,57 @@
 //! Pgm-Index core (no data ownership)
 //! Pgm 索引核心（不持有数据）
+//!
+//! `no_std` + `alloc` compatible, std-only parts are behind the default `std` feature.
+//! 兼容 `no_std` + `alloc`，仅 std 可用的部分由默认的 `std` 特性开启。
 
+#![cfg_attr(not(feature = "std"), no_std)]
 #![allow(clippy::cast_precision_loss)]
-use std::{mem::size_of, ops::Range};
 
+extern crate alloc;
+
+use alloc::{vec, vec::Vec};
+use core::{mem::size_of, ops::Range};
+
+pub mod block;
 pub mod build;
+#[cfg(feature = "bitcode")]
+pub mod compat;
 pub mod consts;
 #[cfg(feature = "data")]
 pub mod data;
+mod dist;
+mod estimate;
+pub mod layout;
+#[cfg(feature = "data")]
+mod near;
+#[cfg(feature = "data")]
+pub mod records;
+#[cfg(feature = "data")]
+pub mod set;
+#[cfg(feature = "shared")]
+pub mod shared;
+pub mod sort;
 pub mod types;
+#[cfg(feature = "data")]
+pub mod zorder;
 
 #[cfg(feature = "data")]
-pub use data::PgmData;
+pub use data::{PgmData, PgmRef};
+#[cfg(feature = "data")]
+pub use records::PgmRecords;
+#[cfg(feature = "data")]
+pub use set::{Difference, Intersect, Union};
+#[cfg(feature = "shared")]
+pub use shared::{PgmGuard, SharedPgm};
+#[cfg(feature = "data")]
+pub use zorder::{ZIndex, ZPoint};
 
 pub use self::{
-  build::{build_lut, build_segments},
+  block::BlockIndex,
+  build::{build_lut, build_segments, build_segments_by},
   consts::MIN_EPSILON,
-  types::{Key, Segment, ToKey},
+  layout::Layout,
+  types::{CountEstimate, Key, KeyBytes, KeyFromF64, Model, Segment, ToKey},
 };
 #[cfg(feature = "data")]
 pub type PgmIndex<K> = PgmData<K>;
@@ -27,17 +62,58 @@ pub type PgmIndex<K> = PgmData<K>;
 #[derive(Clone, Debug)]
 pub struct Pgm<K: Key> {
   pub epsilon: usize,
+  /// Segments in order (`Layout::Lut` only, the other layouts keep the dense
+  /// arrays below)
+  /// 按序排列的段（仅 `Layout::Lut`，其余布局保存下方的紧凑数组）
   pub segments: Vec<Segment<K>>,
   pub lut: Vec<u32>,
   pub scale: f64,
   pub min_key: f64,
   pub len: usize,
+  /// Segment search layout
+  /// 段搜索布局
+  pub layout: Layout,
+  /// Segment max keys in search order (empty for `Layout::Lut`)
+  /// 按搜索顺序排列的段最大键（`Layout::Lut` 时为空）
+  max_keys: Vec<K>,
+  /// Sorted rank of each `max_keys` slot (`Layout::Eytzinger` only)
+  /// `max_keys` 各槽位的有序秩（仅 `Layout::Eytzinger`）
+  ranks: Vec<u32>,
+  /// Segment min keys in sorted order (empty for `Layout::Lut`)
+  /// 按序排列的段最小键（`Layout::Lut` 时为空）
+  min_keys: Vec<K>,
+  /// Segment models in sorted order (empty for `Layout::Lut`)
+  /// 按序排列的段模型（`Layout::Lut` 时为空）
+  models: Vec<Model>,
 }
 
 impl<K: Key> Pgm<K> {
   /// Build Pgm from sorted data slice (O(N) build time)
   /// 从已排序数据切片构建 Pgm
   pub fn new(sorted: &[K], epsilon: usize) -> Self {
+    Self::new_with_layout(sorted, epsilon, Layout::Lut)
+  }
+
+  /// Build Pgm with a custom segment search layout
+  /// 使用自定义段搜索布局构建 Pgm
+  pub fn new_with_layout(sorted: &[K], epsilon: usize, layout: Layout) -> Self {
+    Self::from_sorted_by_with_layout(sorted, |&k| k, epsilon, layout)
+  }
+
+  /// Build Pgm over items sorted by an extracted key, without copying keys out
+  /// 在按提取键排序的元素上构建 Pgm，无需复制出键列
+  pub fn from_sorted_by<T>(sorted: &[T], key: impl Fn(&T) -> K, epsilon: usize) -> Self {
+    Self::from_sorted_by_with_layout(sorted, key, epsilon, Layout::Lut)
+  }
+
+  /// `from_sorted_by` with a custom segment search layout
+  /// 使用自定义段搜索布局的 `from_sorted_by`
+  pub fn from_sorted_by_with_layout<T>(
+    sorted: &[T],
+    key: impl Fn(&T) -> K,
+    epsilon: usize,
+    layout: Layout,
+  ) -> Self {
     let epsilon = epsilon.max(MIN_EPSILON);
     let len = sorted.len();
     if len == 0 {
@@ -48,11 +124,34 @@ impl<K: Key> Pgm<K> {
         scale: 0.0,
         min_key: 0.0,
         len: 0,
+        layout,
+        max_keys: vec![],
+        ranks: vec![],
+        min_keys: vec![],
+        models: vec![],
       };
     }
 
-    let segments = build_segments(sorted, epsilon);
-    let (lut, scale, min_key) = build_lut(sorted, &segments);
+    let segments = build_segments_by(sorted, &key, epsilon);
+    let (lut, scale, min_key) = if layout == Layout::Lut {
+      build::build_lut_by_segments(&segments)
+    } else {
+      // SAFETY: len > 0
+      (
+        vec![0],
+        0.0,
+        key(unsafe { sorted.get_unchecked(0) }).as_f64(),
+      )
+    };
+    let (max_keys, ranks) = layout::build_max_keys(&segments, layout);
+    let (min_keys, models) = layout::build_models(&segments, layout);
+    // The dense arrays replace the segments
+    // 紧凑数组取代段数组
+    let segments = if layout == Layout::Lut {
+      segments
+    } else {
+      vec![]
+    };
 
     Self {
       epsilon,
@@ -61,6 +160,11 @@ impl<K: Key> Pgm<K> {
       scale,
       min_key,
       len,
+      layout,
+      max_keys,
+      ranks,
+      min_keys,
+      models,
     }
   }
 }
@@ -83,13 +187,15 @@ impl<K: Key> Pgm<K> {
   #[inline]
   #[must_use]
   pub fn segment_count(&self) -> usize {
-    self.segments.len()
+    // `Lut` keeps `segments`, the other layouts `models`
+    // `Lut` 保存 `segments`，其余布局保存 `models`
+    self.segments.len().max(self.models.len())
   }
 
   #[inline]
   #[must_use]
   pub fn avg_segment_size(&self) -> f64 {
-    self.len as f64 / self.segments.len().max(1) as f64
+    self.len as f64 / self.segment_count().max(1) as f64
   }
 
   /// Memory usage (excluding data)
@@ -97,7 +203,12 @@ impl<K: Key> Pgm<K> {
   #[inline]
   #[must_use]
   pub fn mem_usage(&self) -> usize {
-    self.segments.len() * size_of::<Segment<K>>() + self.lut.len() * size_of::<u32>()
+    self.segments.len() * size_of::<Segment<K>>()
+      + self.lut.len() * size_of::<u32>()
+      + self.max_keys.len() * size_of::<K>()
+      + self.ranks.len() * size_of::<u32>()
+      + self.min_keys.len() * size_of::<K>()
+      + self.models.len() * size_of::<Model>()
   }
 
   /// Predict position for a key
@@ -105,11 +216,10 @@ impl<K: Key> Pgm<K> {
   #[inline]
   #[must_use]
   pub fn predict(&self, key: K) -> usize {
-    if self.segments.is_empty() {
+    if self.segment_count() == 0 {
       return 0;
     }
-    let seg = self.find_seg(key);
-    predict_in_seg(seg, key.as_f64())
+    predict_in_seg(&self.model(self.find_seg_idx(key)), key.as_f64())
   }
 
   /// Predict search range [start, end) for a key
@@ -125,14 +235,11 @@ impl<K: Key> Pgm<K> {
   #[inline]
   #[must_use]
   pub fn predict_range(&self, key: K) -> Range<usize> {
-    if self.segments.is_empty() {
+    if self.segment_count() == 0 {
       return 0..0;
     }
-    let seg = self.find_seg(key);
-    let pred = predict_in_seg(seg, key.as_f64());
-    let start = pred.saturating_sub(self.epsilon).max(seg.start_idx);
-    let end = (pred + self.epsilon + 1).min(seg.end_idx);
-    start..end
+    let m = self.model(self.find_seg_idx(key));
+    seg_window(&m, predict_in_seg(&m, key.as_f64()))
   }
 
   /// Find index using PGM prediction + binary search (bytes comparison)
@@ -188,81 +295,155 @@ impl<K: Key> Pgm<K> {
     left
   }
 
-  /// Find segment for a key
-  /// 查找键所属的段
+  /// Segment `idx`, rebuilt from the dense arrays when the layout has them
+  /// 下标为 `idx` 的段，布局带紧凑数组时由其重建
+  fn segment(&self, idx: usize) -> Segment<K> {
+    let Some(m) = self.models.get(idx) else {
+      return self.segments[idx];
+    };
+    let slot = match self.layout {
+      Layout::Eytzinger => layout::eytzinger_slot(self.models.len(), idx),
+      _ => idx,
+    };
+    Segment {
+      min_key: self.min_keys[idx],
+      max_key: self.max_keys[slot],
+      slope: m.slope,
+      intercept: m.intercept,
+      start_idx: m.start_idx,
+      end_idx: m.end_idx,
+      err_lo: m.err_lo,
+      err_hi: m.err_hi,
+    }
+  }
+
+  /// Index of the segment for a key
+  /// 键所属段的下标
   #[inline]
-  fn find_seg(&self, key: K) -> &Segment<K> {
-    // SAFETY: This function is only called when self.segments is not empty.
+  fn find_seg_idx(&self, key: K) -> usize {
+    // This function is only called when there are segments.
     // Checked in predict() and predict_range().
-    if self.segments.len() <= 1 {
-      unsafe { self.segments.get_unchecked(0) }
-    } else {
-      let y = key.as_f64();
-      let idx_candidate = (y - self.min_key) * self.scale;
-      let lut_max = (self.lut.len() - 1) as isize;
-
-      let idx_i = idx_candidate as isize;
-      let bin = if idx_i < 0 {
-        0
-      } else if idx_i >= lut_max {
-        lut_max as usize
+    if self.segment_count() <= 1 {
+      return 0;
+    }
+    match self.layout {
+      Layout::Lut => self.find_seg_lut(key),
+      Layout::Sorted => layout::search_sorted(&self.max_keys, key),
+      Layout::Eytzinger => layout::search_eytzinger(&self.max_keys, &self.ranks, key),
+    }
+  }
+
+  /// Model of segment `idx`, from the dense array when the layout has one
+  /// 下标为 `idx` 的段模型，布局带紧凑数组时从中读取
+  #[inline]
+  fn model(&self, idx: usize) -> Model {
+    // SAFETY: idx < segment_count(); `Lut` keeps segments, the rest models
+    unsafe {
+      if self.models.is_empty() {
+        Model::from(self.segments.get_unchecked(idx))
       } else {
-        idx_i as usize
-      };
+        *self.models.get_unchecked(idx)
+      }
+    }
+  }
+
+  /// Find segment index via look-up table and linear walk
+  /// 通过查找表与线性遍历查找段下标
+  #[inline]
+  fn find_seg_lut(&self, key: K) -> usize {
+    let y = key.as_f64();
+    let idx_candidate = (y - self.min_key) * self.scale;
+    let lut_max = (self.lut.len() - 1) as isize;
 
-      // SAFETY: bin is clamped to [0, lut.len()-1]
-      let mut idx = unsafe { *self.lut.get_unchecked(bin) } as usize;
+    let idx_i = idx_candidate as isize;
+    let bin = if idx_i < 0 {
+      0
+    } else if idx_i >= lut_max {
+      lut_max as usize
+    } else {
+      idx_i as usize
+    };
+
+    // SAFETY: bin is clamped to [0, lut.len()-1]
+    let mut idx = unsafe { *self.lut.get_unchecked(bin) } as usize;
 
-      // SAFETY: idx from lut is a valid segment index.
-      // We check bounds in the loops.
-      let mut seg = unsafe { self.segments.get_unchecked(idx) };
+    // SAFETY: idx from lut is a valid segment index.
+    // We check bounds in the loops.
+    let mut seg = unsafe { self.segments.get_unchecked(idx) };
 
-      while idx + 1 < self.segments.len() {
-        if key <= seg.max_key {
-          break;
-        }
-        idx += 1;
-        seg = unsafe { self.segments.get_unchecked(idx) };
+    while idx + 1 < self.segments.len() {
+      if key <= seg.max_key {
+        break;
       }
+      idx += 1;
+      seg = unsafe { self.segments.get_unchecked(idx) };
+    }
 
-      while idx > 0 {
-        if key >= seg.min_key {
-          break;
-        }
-        idx -= 1;
-        seg = unsafe { self.segments.get_unchecked(idx) };
+    while idx > 0 {
+      if key >= seg.min_key {
+        break;
       }
-      seg
+      idx -= 1;
+      seg = unsafe { self.segments.get_unchecked(idx) };
     }
+    idx
   }
 
   /// Find segment containing the given index
   /// 查找包含给定索引的段
   #[inline]
-  pub fn find_segment_for_index(&self, index: usize) -> Option<&Segment<K>> {
+  pub fn find_segment_for_index(&self, index: usize) -> Option<Segment<K>> {
     // Segments are sorted by start_idx.
     // We can binary search.
-    if self.segments.is_empty() {
-      return None;
-    }
-
-    let idx = self.segments.partition_point(|seg| seg.start_idx <= index);
+    let idx = if self.models.is_empty() {
+      self.segments.partition_point(|seg| seg.start_idx <= index)
+    } else {
+      self.models.partition_point(|m| m.start_idx <= index)
+    };
     // partition_point returns the first index where predicate is false.
     // So the segment we want is at idx - 1.
     if idx == 0 {
-      return None; // Should not happen if segments cover 0..len and index < len
+      return None; // No segments, or index before the first one
     }
-    let seg = &self.segments[idx - 1];
+    let seg = self.segment(idx - 1);
     if index < seg.end_idx { Some(seg) } else { None }
   }
 }
 
+/// `a * b + c`, fused when std is available
+/// `a * b + c`，有 std 时使用融合乘加
+#[inline(always)]
+fn mul_add(a: f64, b: f64, c: f64) -> f64 {
+  #[cfg(feature = "std")]
+  {
+    a.mul_add(b, c)
+  }
+  #[cfg(not(feature = "std"))]
+  {
+    a * b + c
+  }
+}
+
 /// Predict index position using segment's linear model
 /// 使用段的线性模型预测索引位置
 #[inline]
-fn predict_in_seg(seg: &Segment<impl Key>, key_f64: f64) -> usize {
-  let pos = seg.slope.mul_add(key_f64, seg.intercept) + 0.5;
+fn predict_in_seg(seg: &Model, key_f64: f64) -> usize {
+  let pos = mul_add(seg.slope, key_f64, seg.intercept) + 0.5;
   let lo = seg.start_idx;
   let hi = seg.end_idx - 1;
   (pos as usize).clamp(lo, hi)
 }
+
+/// Last-mile search window around `pred` from the segment's recorded errors
+/// 根据段记录的误差得到 `pred` 附近的最后一段查找窗口
+///
+/// The model is monotone, so a key between two indexed keys predicts between
+/// their predictions, and its lower bound stays inside this window.
+/// 模型单调，位于两个已索引键之间的键，其预测也在二者预测之间，
+/// 因此其下界位置仍在该窗口内。
+#[inline]
+fn seg_window(seg: &Model, pred: usize) -> Range<usize> {
+  let start = pred.saturating_sub(seg.err_lo as usize).max(seg.start_idx);
+  let end = (pred + seg.err_hi as usize + 1).min(seg.end_idx);
+  start..end
+}
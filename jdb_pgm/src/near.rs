@@ -0,0 +1,62 @@
+//! Nearest-key queries by numeric distance
+//! 按数值距离的最近键查询
+//!
+//! Both queries start at the `find_key` position and expand outward one key at a
+//! time. Equal distances resolve to the smaller key, so results are
+//! deterministic at ties and at the `min_key` / `max_key` edges.
+//! 两种查询都从 `find_key` 位置出发，每次向外扩展一个键。距离相等时取较小
+//! 的键，因此在平局及 `min_key` / `max_key` 边界处结果确定。
+
+use core::ops::Range;
+
+use crate::{Key, PgmData};
+
+impl<K: Key, S: AsRef<[K]>> PgmData<K, S> {
+  /// Position of the key closest to `key` (None if empty)
+  /// 距 `key` 最近的键的位置（为空则返回 None）
+  ///
+  /// Ties go to the smaller key; among duplicates the first position is returned.
+  /// 距离相等时取较小的键；重复键返回第一个位置。
+  #[must_use]
+  pub fn nearest(&self, key: K) -> Option<usize> {
+    let Range { start, end } = self.k_nearest(key, 1);
+    if start == end {
+      return None;
+    }
+    let sorted = self.sorted();
+    let k = sorted[start];
+    Some(sorted[..start].partition_point(|&x| x < k))
+  }
+
+  /// Positions of the `k` keys closest to `key`, as a window of `sorted`
+  /// 距 `key` 最近的 `k` 个键的位置，为 `sorted` 上的一个窗口
+  ///
+  /// The k nearest keys are always contiguous, so the window is in key order,
+  /// not distance order. Shorter than `k` only when fewer keys exist.
+  /// 最近的 k 个键总是连续的，因此窗口按键序而非距离排序。仅当键数不足时
+  /// 短于 `k`。
+  #[must_use]
+  pub fn k_nearest(&self, key: K, k: usize) -> Range<usize> {
+    let sorted = self.sorted();
+    let n = sorted.len();
+    let k = k.min(n);
+    // SAFETY: Pgm::find_key callback is only called with indices within data bounds
+    let pos = self
+      .pgm
+      .find_key(key, |i| unsafe { Some(*sorted.get_unchecked(i)) })
+      .min(n);
+    let (mut l, mut r) = (pos, pos);
+    while r - l < k {
+      if l == 0 {
+        r = k;
+      } else if r == n {
+        l = n - k;
+      } else if key.distance(sorted[l - 1]) <= sorted[r].distance(key) {
+        l -= 1;
+      } else {
+        r += 1;
+      }
+    }
+    l..r
+  }
+}
@@ -0,0 +1,129 @@
+//! Pgm-Index owning records sorted by an extracted key
+//! 持有按提取键排序的记录的 Pgm 索引
+//!
+//! The key column is never materialized: the model is built with
+//! `Pgm::from_sorted_by` and the last-mile search reads keys through the
+//! extractor.
+//! 不单独生成键列：模型由 `Pgm::from_sorted_by` 构建，最后一段查找通过
+//! 提取函数读取键。
+
+use alloc::vec::Vec;
+use core::{
+  mem::size_of,
+  ops::{Bound, Deref, RangeBounds},
+};
+
+use crate::{Key, Pgm};
+
+/// Pgm-Index owning records, keyed by `key(&record)`
+/// 持有记录的 Pgm 索引，键为 `key(&record)`
+#[derive(Clone)]
+pub struct PgmRecords<T, K: Key, F: Fn(&T) -> K> {
+  pub pgm: Pgm<K>,
+  records: Vec<T>,
+  key: F,
+}
+
+impl<T, K: Key, F: Fn(&T) -> K> Deref for PgmRecords<T, K, F> {
+  type Target = Pgm<K>;
+
+  #[inline]
+  fn deref(&self) -> &Self::Target {
+    &self.pgm
+  }
+}
+
+impl<T, K: Key, F: Fn(&T) -> K> PgmRecords<T, K, F> {
+  /// Take ownership of records already sorted by `key`
+  /// 接管已按 `key` 排序的记录
+  pub fn new(records: Vec<T>, key: F, epsilon: usize) -> Self {
+    debug_assert!(records.windows(2).all(|w| key(&w[0]) <= key(&w[1])));
+    Self {
+      pgm: Pgm::from_sorted_by(&records, &key, epsilon),
+      records,
+      key,
+    }
+  }
+
+  /// Get reference to underlying records
+  /// 获取底层记录引用
+  #[inline]
+  #[must_use]
+  pub fn records(&self) -> &[T] {
+    &self.records
+  }
+
+  /// Give back the records
+  /// 交还记录
+  #[inline]
+  #[must_use]
+  pub fn into_records(self) -> Vec<T> {
+    self.records
+  }
+
+  /// Key of the record at `i`
+  /// 位置 `i` 处记录的键
+  #[inline]
+  fn key_at(&self, i: usize) -> K {
+    // SAFETY: Pgm::find_key only passes indices within the predicted range,
+    // which is bounded by records.len()
+    (self.key)(unsafe { self.records.get_unchecked(i) })
+  }
+
+  /// First position whose key is not less than `key`
+  /// 第一个键不小于 `key` 的位置
+  #[inline]
+  fn lower_bound(&self, key: K) -> usize {
+    self.pgm.find_key(key, |i| Some(self.key_at(i)))
+  }
+
+  /// First position whose key is greater than `key`
+  /// 第一个键大于 `key` 的位置
+  #[inline]
+  fn upper_bound(&self, key: K) -> usize {
+    let pos = self.lower_bound(key);
+    pos + self.records[pos..].partition_point(|r| (self.key)(r) <= key)
+  }
+
+  /// Position of the first record with `key` (None if absent)
+  /// 第一个键为 `key` 的记录位置（不存在则返回 None）
+  #[inline]
+  #[must_use]
+  pub fn position(&self, key: K) -> Option<usize> {
+    let pos = self.lower_bound(key);
+    (pos < self.records.len() && self.key_at(pos) == key).then_some(pos)
+  }
+
+  /// First record with `key` (None if absent)
+  /// 第一个键为 `key` 的记录（不存在则返回 None）
+  #[inline]
+  #[must_use]
+  pub fn get(&self, key: K) -> Option<&T> {
+    self.position(key).map(|i| &self.records[i])
+  }
+
+  /// Records whose key falls in `range`, in key order
+  /// 键落在 `range` 内的记录，按键序
+  #[must_use]
+  pub fn range<R: RangeBounds<K>>(&self, range: R) -> &[T] {
+    let start = match range.start_bound() {
+      Bound::Included(&k) => self.lower_bound(k),
+      Bound::Excluded(&k) => self.upper_bound(k),
+      Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+      Bound::Included(&k) => self.upper_bound(k),
+      Bound::Excluded(&k) => self.lower_bound(k),
+      Bound::Unbounded => self.records.len(),
+    };
+    &self.records[start..end.max(start)]
+  }
+
+  /// Memory usage (including records)
+  /// 内存占用（含记录）
+  #[inline]
+  #[must_use]
+  pub fn memory_usage(&self) -> usize {
+    self.records.len() * size_of::<T>() + self.pgm.mem_usage()
+  }
+}
@@ -0,0 +1,378 @@
+//! Sorted set operations over PgmData (intersect / union / difference)
+//! 基于 PgmData 的有序集合运算（交集 / 并集 / 差集）
+//!
+//! When list sizes differ by at least `SKIP_RATIO`, the smaller list drives
+//! and the larger one is probed with `predict_range`, skipping whole runs
+//! instead of comparing every element. Otherwise a plain linear merge is used.
+//! 当两表长度相差至少 `SKIP_RATIO` 倍时，由短表驱动，用 `predict_range`
+//! 探测长表，整段跳过而不逐个比较；否则使用普通线性归并。
+//!
+//! Duplicates follow merge semantics: the i-th equal key of one side matches
+//! the i-th equal key of the other.
+//! 重复键遵循归并语义：一侧第 i 个相等键与另一侧第 i 个相等键配对。
+
+use core::ops::Range;
+
+use crate::{Key, Pgm, PgmData, consts::SKIP_RATIO};
+
+/// Lookup strategy
+/// 查找策略
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode {
+  /// Linear merge
+  /// 线性归并
+  Merge,
+  /// Drive with `a`, probe `b` by learned skip
+  /// 以 `a` 驱动，学习型跳跃探测 `b`
+  ProbeB,
+  /// Drive with `b`, probe `a` by learned skip
+  /// 以 `b` 驱动，学习型跳跃探测 `a`
+  ProbeA,
+}
+
+#[inline]
+fn pick_mode(a: usize, b: usize) -> Mode {
+  if a.saturating_mul(SKIP_RATIO) <= b {
+    Mode::ProbeB
+  } else if b.saturating_mul(SKIP_RATIO) <= a {
+    Mode::ProbeA
+  } else {
+    Mode::Merge
+  }
+}
+
+/// Borrowed model + keys of one operand
+/// 单个操作数借用的模型与键
+#[derive(Clone, Copy)]
+struct View<'a, K: Key> {
+  pgm: &'a Pgm<K>,
+  sorted: &'a [K],
+}
+
+impl<'a, K: Key> View<'a, K> {
+  #[inline]
+  fn new<S: AsRef<[K]>>(data: &'a PgmData<K, S>) -> Self {
+    Self {
+      pgm: &data.pgm,
+      sorted: data.sorted(),
+    }
+  }
+}
+
+/// First position >= `from` whose key is not less than `key`
+/// 从 `from` 起第一个不小于 `key` 的位置
+#[inline]
+pub(crate) fn lower_bound_from<K: Key>(pgm: &Pgm<K>, sorted: &[K], key: K, from: usize) -> usize {
+  let range = pgm.predict_range(key);
+  let lo = range.start.max(from);
+  let hi = range.end.max(lo);
+  let pos = lo + sorted[lo..hi].partition_point(|&k| k < key);
+
+  // Verify the learned guess, fall back to binary search on miss
+  // 校验学习型预测，未命中则回退到二分查找
+  let left_ok = pos == from || sorted[pos - 1] < key;
+  let right_ok = pos == sorted.len() || sorted[pos] >= key;
+  if left_ok && right_ok {
+    pos
+  } else {
+    from + sorted[from..].partition_point(|&k| k < key)
+  }
+}
+
+/// One learned step: advance driver `d` until a key matches in `probe`
+/// 一次学习型步进：推进驱动表 `d` 直到在 `probe` 中命中
+#[inline]
+fn probe_step<K: Key>(
+  drive: &[K],
+  probe: View<'_, K>,
+  i: &mut usize,
+  j: &mut usize,
+) -> Option<(usize, usize)> {
+  let len = probe.sorted.len();
+  while *i < drive.len() {
+    // SAFETY: *i < drive.len() checked by loop condition
+    let key = unsafe { *drive.get_unchecked(*i) };
+    let pos = lower_bound_from(probe.pgm, probe.sorted, key, *j);
+    if pos == len {
+      *i = drive.len();
+      return None;
+    }
+    *i += 1;
+    // SAFETY: pos < len checked above
+    if unsafe { *probe.sorted.get_unchecked(pos) } == key {
+      *j = pos + 1;
+      return Some((*i - 1, pos));
+    }
+    *j = pos;
+  }
+  None
+}
+
+/// Intersection iterator yielding `(pos_in_self, pos_in_other)`
+/// 交集迭代器，产出 `(self 中位置, other 中位置)`
+pub struct Intersect<'a, K: Key> {
+  a: View<'a, K>,
+  b: View<'a, K>,
+  i: usize,
+  j: usize,
+  mode: Mode,
+}
+
+impl<K: Key> Iterator for Intersect<'_, K> {
+  type Item = (usize, usize);
+
+  fn next(&mut self) -> Option<Self::Item> {
+    match self.mode {
+      Mode::Merge => {
+        let (a, b) = (self.a.sorted, self.b.sorted);
+        while self.i < a.len() && self.j < b.len() {
+          let (x, y) = (a[self.i], b[self.j]);
+          if x < y {
+            self.i += 1;
+          } else if x > y {
+            self.j += 1;
+          } else {
+            let r = (self.i, self.j);
+            self.i += 1;
+            self.j += 1;
+            return Some(r);
+          }
+        }
+        None
+      }
+      Mode::ProbeB => probe_step(self.a.sorted, self.b, &mut self.i, &mut self.j),
+      Mode::ProbeA => {
+        probe_step(self.b.sorted, self.a, &mut self.j, &mut self.i).map(|(j, i)| (i, j))
+      }
+    }
+  }
+
+  #[inline]
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    let rest_a = self.a.sorted.len() - self.i;
+    let rest_b = self.b.sorted.len() - self.j;
+    (0, Some(rest_a.min(rest_b)))
+  }
+}
+
+/// Union iterator yielding keys in ascending order
+/// 并集迭代器，按升序产出键
+pub struct Union<'a, K: Key> {
+  a: View<'a, K>,
+  b: View<'a, K>,
+  i: usize,
+  j: usize,
+  mode: Mode,
+  /// Pending run of the larger side (learned mode)
+  /// 长表待输出区间（学习模式）
+  run: Range<usize>,
+  /// Pending key of the smaller side (learned mode)
+  /// 短表待输出键（学习模式）
+  pending: Option<K>,
+}
+
+impl<K: Key> Union<'_, K> {
+  /// Learned union step: `small` drives, `large` emits whole runs
+  /// 学习型并集步进：短表驱动，长表整段输出
+  fn next_learned(&mut self, small: &[K], large: View<'_, K>, si: usize, li: usize) -> Option<K> {
+    let (mut s, mut l) = (si, li);
+    let out = loop {
+      if self.run.start < self.run.end {
+        let k = large.sorted[self.run.start];
+        self.run.start += 1;
+        break Some(k);
+      }
+      if let Some(k) = self.pending.take() {
+        break Some(k);
+      }
+      if s < small.len() {
+        let key = small[s];
+        s += 1;
+        let pos = lower_bound_from(large.pgm, large.sorted, key, l);
+        self.run = l..pos;
+        l = if pos < large.sorted.len() && large.sorted[pos] == key {
+          pos + 1
+        } else {
+          pos
+        };
+        self.pending = Some(key);
+      } else if l < large.sorted.len() {
+        self.run = l..large.sorted.len();
+        l = large.sorted.len();
+      } else {
+        break None;
+      }
+    };
+    (self.i, self.j) = if self.mode == Mode::ProbeB {
+      (s, l)
+    } else {
+      (l, s)
+    };
+    out
+  }
+}
+
+impl<K: Key> Iterator for Union<'_, K> {
+  type Item = K;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    match self.mode {
+      Mode::Merge => {
+        let (a, b) = (self.a.sorted, self.b.sorted);
+        match (a.get(self.i), b.get(self.j)) {
+          (Some(&x), Some(&y)) => {
+            if x < y {
+              self.i += 1;
+              Some(x)
+            } else if x > y {
+              self.j += 1;
+              Some(y)
+            } else {
+              self.i += 1;
+              self.j += 1;
+              Some(x)
+            }
+          }
+          (Some(&x), None) => {
+            self.i += 1;
+            Some(x)
+          }
+          (None, Some(&y)) => {
+            self.j += 1;
+            Some(y)
+          }
+          (None, None) => None,
+        }
+      }
+      Mode::ProbeB => {
+        let (a, b) = (self.a, self.b);
+        self.next_learned(a.sorted, b, self.i, self.j)
+      }
+      Mode::ProbeA => {
+        let (a, b) = (self.a, self.b);
+        self.next_learned(b.sorted, a, self.j, self.i)
+      }
+    }
+  }
+}
+
+/// Difference iterator yielding positions in self of keys absent from other
+/// 差集迭代器，产出 self 中不在 other 内的键的位置
+pub struct Difference<'a, K: Key> {
+  a: View<'a, K>,
+  b: View<'a, K>,
+  i: usize,
+  j: usize,
+  mode: Mode,
+  /// Pending run of self positions (ProbeA mode)
+  /// self 待输出位置区间（ProbeA 模式）
+  run: Range<usize>,
+}
+
+impl<K: Key> Iterator for Difference<'_, K> {
+  type Item = usize;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let (a, b) = (self.a.sorted, self.b.sorted);
+    match self.mode {
+      Mode::Merge => {
+        while self.i < a.len() {
+          let Some(&y) = b.get(self.j) else {
+            self.i += 1;
+            return Some(self.i - 1);
+          };
+          let x = a[self.i];
+          if x < y {
+            self.i += 1;
+            return Some(self.i - 1);
+          }
+          self.j += 1;
+          if x == y {
+            self.i += 1;
+          }
+        }
+        None
+      }
+      Mode::ProbeB => {
+        while self.i < a.len() {
+          let key = a[self.i];
+          self.i += 1;
+          let pos = lower_bound_from(self.b.pgm, b, key, self.j);
+          if pos < b.len() && b[pos] == key {
+            self.j = pos + 1;
+          } else {
+            self.j = pos;
+            return Some(self.i - 1);
+          }
+        }
+        None
+      }
+      Mode::ProbeA => loop {
+        if self.run.start < self.run.end {
+          self.run.start += 1;
+          return Some(self.run.start - 1);
+        }
+        if self.j < b.len() {
+          let key = b[self.j];
+          self.j += 1;
+          let pos = lower_bound_from(self.a.pgm, a, key, self.i);
+          self.run = self.i..pos;
+          self.i = if pos < a.len() && a[pos] == key {
+            pos + 1
+          } else {
+            pos
+          };
+        } else if self.i < a.len() {
+          self.run = self.i..a.len();
+          self.i = a.len();
+        } else {
+          return None;
+        }
+      },
+    }
+  }
+}
+
+impl<K: Key, S: AsRef<[K]>> PgmData<K, S> {
+  /// Intersect with another index, yielding `(pos_in_self, pos_in_other)`
+  /// 与另一索引求交集，产出 `(self 中位置, other 中位置)`
+  #[inline]
+  pub fn intersect<'a, S2: AsRef<[K]>>(&'a self, other: &'a PgmData<K, S2>) -> Intersect<'a, K> {
+    Intersect {
+      a: View::new(self),
+      b: View::new(other),
+      i: 0,
+      j: 0,
+      mode: pick_mode(self.sorted().len(), other.sorted().len()),
+    }
+  }
+
+  /// Union with another index, yielding keys in ascending order
+  /// 与另一索引求并集，按升序产出键
+  #[inline]
+  pub fn union<'a, S2: AsRef<[K]>>(&'a self, other: &'a PgmData<K, S2>) -> Union<'a, K> {
+    Union {
+      a: View::new(self),
+      b: View::new(other),
+      i: 0,
+      j: 0,
+      mode: pick_mode(self.sorted().len(), other.sorted().len()),
+      run: 0..0,
+      pending: None,
+    }
+  }
+
+  /// Keys of self absent from other, yielding positions in self
+  /// self 中不在 other 内的键，产出 self 中的位置
+  #[inline]
+  pub fn difference<'a, S2: AsRef<[K]>>(&'a self, other: &'a PgmData<K, S2>) -> Difference<'a, K> {
+    Difference {
+      a: View::new(self),
+      b: View::new(other),
+      i: 0,
+      j: 0,
+      mode: pick_mode(self.sorted().len(), other.sorted().len()),
+      run: 0..0,
+    }
+  }
+}
@@ -0,0 +1,166 @@
+//! Shared PgmData handle with atomic swap for concurrent readers
+//! 支持原子替换的共享 PgmData 句柄，供并发读者使用
+//!
+//! Readers never block or retry: `load` is two counter updates plus one
+//! refcount increment. `publish` swaps the pointer, then waits for the
+//! in-flight loads of both reader epochs to drain before releasing its own
+//! reference. An old version is freed when its last guard drops.
+//! 读者从不阻塞或重试：`load` 仅包含两次计数器更新与一次引用计数递增。
+//! `publish` 替换指针后，等待两个读者纪元中进行中的 load 结束，再释放
+//! 自身持有的引用。旧版本在最后一个 guard 释放时回收。
+
+use std::{
+  hint,
+  ops::Deref,
+  ptr,
+  sync::{
+    Arc, Mutex,
+    atomic::{AtomicPtr, AtomicUsize, Ordering::SeqCst},
+  },
+  thread::{self, JoinHandle},
+};
+
+use crate::{Key, PgmData};
+
+/// Counter on its own cache line
+/// 独占缓存行的计数器
+#[derive(Default)]
+#[repr(align(64))]
+struct Padded(AtomicUsize);
+
+/// Read guard keeping one index version alive
+/// 读守卫，保持某一索引版本存活
+#[derive(Clone, Debug)]
+pub struct PgmGuard<K: Key>(Arc<PgmData<K>>);
+
+impl<K: Key> PgmGuard<K> {
+  /// Take the underlying `Arc`
+  /// 取出底层 `Arc`
+  #[inline]
+  #[must_use]
+  pub fn into_inner(self) -> Arc<PgmData<K>> {
+    self.0
+  }
+}
+
+impl<K: Key> Deref for PgmGuard<K> {
+  type Target = PgmData<K>;
+
+  #[inline]
+  fn deref(&self) -> &Self::Target {
+    &self.0
+  }
+}
+
+/// Shared index handle: wait-free `load`, atomic `publish`
+/// 共享索引句柄：无等待 `load`，原子 `publish`
+pub struct SharedPgm<K: Key> {
+  /// Raw pointer from `Arc::into_raw`
+  /// 来自 `Arc::into_raw` 的裸指针
+  ptr: AtomicPtr<PgmData<K>>,
+  epoch: AtomicUsize,
+  readers: [Padded; 2],
+  /// Serializes publishers and rebuilds
+  /// 串行化发布者与重建
+  writer: Mutex<()>,
+}
+
+impl<K: Key> SharedPgm<K> {
+  /// Wrap an initial index
+  /// 包装初始索引
+  pub fn new(index: PgmData<K>) -> Self {
+    Self {
+      ptr: AtomicPtr::new(Arc::into_raw(Arc::new(index)).cast_mut()),
+      epoch: AtomicUsize::new(0),
+      readers: Default::default(),
+      writer: Mutex::new(()),
+    }
+  }
+
+  /// Get the current index version (wait-free)
+  /// 获取当前索引版本（无等待）
+  #[inline]
+  pub fn load(&self) -> PgmGuard<K> {
+    let slot = &self.readers[self.epoch.load(SeqCst) & 1].0;
+    slot.fetch_add(1, SeqCst);
+    let p = self.ptr.load(SeqCst);
+    // SAFETY: `p` came from `Arc::into_raw`, and `publish` does not release
+    // its reference until this slot drains, so the count is still >= 1.
+    let arc = unsafe {
+      Arc::increment_strong_count(p);
+      Arc::from_raw(p)
+    };
+    slot.fetch_sub(1, SeqCst);
+    PgmGuard(arc)
+  }
+
+  /// Atomically replace the index, returning the previous version
+  /// 原子替换索引，返回之前的版本
+  pub fn publish(&self, index: PgmData<K>) -> Arc<PgmData<K>> {
+    let _lock = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+    self.swap(Arc::new(index))
+  }
+
+  /// Rebuild from the current snapshot plus sorted-or-not `delta` keys on a
+  /// background thread, then publish. Rebuilds and publishes are serialized,
+  /// so no concurrent update is lost.
+  /// 在后台线程上以当前快照加 `delta` 键（可无序）重建并发布。重建与发布
+  /// 串行执行，不会丢失并发更新。
+  pub fn spawn_rebuild(self: &Arc<Self>, mut delta: Vec<K>) -> JoinHandle<()> {
+    let shared = Arc::clone(self);
+    thread::spawn(move || {
+      let _lock = shared.writer.lock().unwrap_or_else(|e| e.into_inner());
+      let snap = shared.load();
+      delta.sort_unstable();
+      let merged = merge(snap.sorted(), &delta);
+      let index = PgmData::new(&merged, snap.pgm.epsilon);
+      drop(snap);
+      shared.swap(Arc::new(index));
+    })
+  }
+
+  /// Swap pointer and wait for in-flight loads (caller holds `writer`)
+  /// 替换指针并等待进行中的 load 结束（调用方持有 `writer`）
+  fn swap(&self, next: Arc<PgmData<K>>) -> Arc<PgmData<K>> {
+    let old = self.ptr.swap(Arc::into_raw(next).cast_mut(), SeqCst);
+    // A reader that saw `old` registered in one of the two slots before the
+    // swap; flipping twice waits on both.
+    // 看到 `old` 的读者在替换前已登记到两个槽之一；翻转两次即可等待二者。
+    for _ in 0..2 {
+      let slot = &self.readers[self.epoch.fetch_add(1, SeqCst) & 1].0;
+      while slot.load(SeqCst) != 0 {
+        hint::spin_loop();
+      }
+    }
+    // SAFETY: `old` came from `Arc::into_raw` and no reader can still reach
+    // it without holding its own count.
+    unsafe { Arc::from_raw(old) }
+  }
+}
+
+impl<K: Key> Drop for SharedPgm<K> {
+  fn drop(&mut self) {
+    let p = self.ptr.swap(ptr::null_mut(), SeqCst);
+    // SAFETY: `&mut self` guarantees no concurrent load.
+    drop(unsafe { Arc::from_raw(p) });
+  }
+}
+
+/// Merge two sorted slices keeping duplicates
+/// 合并两个有序切片，保留重复项
+fn merge<K: Key>(a: &[K], b: &[K]) -> Vec<K> {
+  let mut out = Vec::with_capacity(a.len() + b.len());
+  let (mut i, mut j) = (0, 0);
+  while i < a.len() && j < b.len() {
+    if b[j] < a[i] {
+      out.push(b[j]);
+      j += 1;
+    } else {
+      out.push(a[i]);
+      i += 1;
+    }
+  }
+  out.extend_from_slice(&a[i..]);
+  out.extend_from_slice(&b[j..]);
+  out
+}
@@ -0,0 +1,109 @@
+//! Learned sort: bucket keys by a sample-built Pgm, then sort locally
+//! 学习型排序：用采样构建的 Pgm 分桶，再做局部排序
+//!
+//! 1. Sample keys and build a `Pgm` over the sorted sample
+//! 2. Scatter keys into buckets by `cdf`, which is monotone in the key
+//! 3. Sort each bucket with `sort_unstable`
+//!
+//! 1. 采样键并在有序样本上构建 `Pgm`
+//! 2. 按 `cdf` 把键分散到桶中（`cdf` 随键单调）
+//! 3. 对每个桶执行 `sort_unstable`
+//!
+//! If the sample predicts badly (one bucket gets too many keys), the whole
+//! slice falls back to `sort_unstable` before any scatter.
+//! 若样本预测失准（某桶过满），在分散前整体回退到 `sort_unstable`。
+
+use alloc::{vec, vec::Vec};
+
+use crate::{
+  Key, Pgm,
+  consts::{SORT_BUCKET_LEN, SORT_MIN_LEN},
+};
+
+/// Pgm epsilon used for the sample model
+/// 样本模型使用的 Pgm epsilon
+const SAMPLE_EPSILON: usize = 4;
+
+/// Sort `data` in place using a learned CDF model
+/// 使用学习型 CDF 模型原地排序 `data`
+pub fn learned_sort<K: Key>(data: &mut [K]) {
+  let n = data.len();
+  if n < SORT_MIN_LEN {
+    data.sort_unstable();
+    return;
+  }
+
+  let pgm = sample_model(data);
+  let buckets = n / SORT_BUCKET_LEN;
+  let scale = buckets as f64;
+  let bucket_of = |k: K| ((pgm.cdf(k) * scale) as usize).min(buckets - 1);
+
+  // Pass 1: bucket sizes
+  // 第 1 遍：统计桶大小
+  let mut ids = Vec::with_capacity(n);
+  let mut offsets = vec![0usize; buckets + 1];
+  for &k in data.iter() {
+    let b = bucket_of(k);
+    ids.push(b as u32);
+    offsets[b + 1] += 1;
+  }
+
+  // Adversarial distribution: skip the scatter entirely
+  // 对抗性分布：完全跳过分散
+  let max_bucket = offsets.iter().copied().max().unwrap_or(0);
+  if max_bucket > n / 4 {
+    data.sort_unstable();
+    return;
+  }
+
+  for b in 0..buckets {
+    offsets[b + 1] += offsets[b];
+  }
+
+  // Pass 2: scatter
+  // 第 2 遍：分散
+  let mut out = data.to_vec();
+  let mut cursor = offsets.clone();
+  for (&k, &b) in data.iter().zip(&ids) {
+    let c = &mut cursor[b as usize];
+    out[*c] = k;
+    *c += 1;
+  }
+
+  // Pass 3: local sort
+  // 第 3 遍：局部排序
+  for w in offsets.windows(2) {
+    out[w[0]..w[1]].sort_unstable();
+  }
+
+  // Buckets are ordered as long as the model is monotone; verify the seams
+  // 模型单调时桶间有序；校验桶间接缝
+  let seams_ok = offsets[1..buckets]
+    .iter()
+    .all(|&o| o == 0 || o == n || out[o - 1] <= out[o]);
+  if !seams_ok {
+    out.sort_unstable();
+  }
+  data.copy_from_slice(&out);
+}
+
+/// Build the CDF model from an evenly strided, jittered sample
+/// 基于等距加扰动的样本构建 CDF 模型
+fn sample_model<K: Key>(data: &[K]) -> Pgm<K> {
+  let n = data.len();
+  let m = (n / 64).clamp(SORT_MIN_LEN / 4, 1 << 16);
+  let stride = n / m;
+  let mut state = n as u64 ^ 0x9E37_79B9_7F4A_7C15;
+  let mut sample: Vec<K> = (0..m)
+    .map(|i| {
+      // xorshift jitter inside each stride, avoids aliasing with periodic input
+      // 在步长内做 xorshift 扰动，避免与周期性输入混叠
+      state ^= state << 13;
+      state ^= state >> 7;
+      state ^= state << 17;
+      data[i * stride + (state % stride as u64) as usize]
+    })
+    .collect();
+  sample.sort_unstable();
+  Pgm::new(&sample, SAMPLE_EPSILON)
+}
@@ -3,7 +3,8 @@
 
 #![allow(clippy::cast_precision_loss, clippy::cast_lossless)]
 
-use std::fmt::Debug;
+use alloc::{boxed::Box, vec::Vec};
+use core::{fmt::Debug, mem::size_of};
 
 /// Key trait for supported types
 /// 支持的键类型约束
@@ -11,6 +12,37 @@ pub trait Key: Copy + Send + Sync + Ord + Debug + 'static {
   /// Convert to f64
   /// 转换为 f64
   fn as_f64(self) -> f64;
+
+  /// Absolute numeric distance `|self - other|`
+  /// 数值距离 `|self - other|`
+  #[inline]
+  fn distance(self, other: Self) -> u128 {
+    (self.as_f64() - other.as_f64()).abs() as u128
+  }
+}
+
+/// Key that can be rebuilt from an f64 position solve (used by `Pgm::quantile`)
+/// 可由 f64 求解结果还原的键（供 `Pgm::quantile` 使用）
+pub trait KeyFromF64: Key {
+  /// Convert from f64 (saturating, truncating toward zero)
+  /// 从 f64 转换（饱和，向零截断）
+  fn from_f64(v: f64) -> Self;
+}
+
+/// Fixed-width little-endian encoding of a key
+/// 键的定宽小端编码
+pub trait KeyBytes: Key {
+  /// Encoded size in bytes
+  /// 编码字节数
+  const SIZE: usize;
+
+  /// Append little-endian bytes
+  /// 追加小端字节
+  fn put_le(self, out: &mut Vec<u8>);
+
+  /// Read from exactly `SIZE` little-endian bytes
+  /// 从恰好 `SIZE` 个小端字节读取
+  fn get_le(bytes: &[u8]) -> Self;
 }
 
 /// Trait for types that can be converted to Key and provide bytes reference
@@ -33,6 +65,18 @@ macro_rules! impl_key {
         fn as_f64(self) -> f64 {
           self as f64
         }
+
+        #[inline(always)]
+        fn distance(self, other: Self) -> u128 {
+          self.abs_diff(other) as u128
+        }
+      }
+
+      impl KeyFromF64 for $t {
+        #[inline(always)]
+        fn from_f64(v: f64) -> Self {
+          v as $t
+        }
       }
     )*
   };
@@ -42,6 +86,32 @@ impl_key!(
   u8, i8, u16, i16, u32, i32, u64, i64, u128, i128, usize, isize
 );
 
+macro_rules! impl_key_bytes {
+  ($($t:ty),*) => {
+    $(
+      impl KeyBytes for $t {
+        const SIZE: usize = size_of::<$t>();
+
+        #[inline]
+        fn put_le(self, out: &mut Vec<u8>) {
+          out.extend_from_slice(&self.to_le_bytes());
+        }
+
+        #[inline]
+        fn get_le(bytes: &[u8]) -> Self {
+          let mut buf = [0u8; size_of::<$t>()];
+          buf.copy_from_slice(bytes);
+          <$t>::from_le_bytes(buf)
+        }
+      }
+    )*
+  };
+}
+
+// usize / isize are left out: their width is platform dependent
+// 不包含 usize / isize：其宽度与平台相关
+impl_key_bytes!(u8, i8, u16, i16, u32, i32, u64, i64, u128, i128);
+
 /// Helper to convert bytes to u64 (big-endian, pad with 0)
 /// 将字节转换为 u64（大端序，不足补0）
 #[inline]
@@ -107,6 +177,40 @@ pub struct Segment<K: Key> {
   pub intercept: f64,
   pub start_idx: usize,
   pub end_idx: usize,
+  /// Max `pred - idx` over the segment's keys (window left of prediction)
+  /// 段内键的最大 `pred - idx`（预测左侧窗口）
+  pub err_lo: u32,
+  /// Max `idx - pred` over the segment's keys (window right of prediction)
+  /// 段内键的最大 `idx - pred`（预测右侧窗口）
+  pub err_hi: u32,
+}
+
+/// Segment model without its key range, stored densely for search layouts
+/// 不含键范围的段模型，供搜索布局紧凑存放
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+pub struct Model {
+  pub slope: f64,
+  pub intercept: f64,
+  pub start_idx: usize,
+  pub end_idx: usize,
+  pub err_lo: u32,
+  pub err_hi: u32,
+}
+
+impl<K: Key> From<&Segment<K>> for Model {
+  #[inline(always)]
+  fn from(seg: &Segment<K>) -> Self {
+    Self {
+      slope: seg.slope,
+      intercept: seg.intercept,
+      start_idx: seg.start_idx,
+      end_idx: seg.end_idx,
+      err_lo: seg.err_lo,
+      err_hi: seg.err_hi,
+    }
+  }
 }
 
 /// Index statistics
@@ -118,3 +222,18 @@ pub struct PgmStats {
   pub avg_segment_size: f64,
   pub memory_bytes: usize,
 }
+
+/// Estimated key count with guaranteed bounds
+/// 带保证边界的键数量估计
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CountEstimate {
+  /// Model estimate
+  /// 模型估计值
+  pub estimate: usize,
+  /// True count is >= lower
+  /// 真实数量 >= lower
+  pub lower: usize,
+  /// True count is <= upper
+  /// 真实数量 <= upper
+  pub upper: usize,
+}
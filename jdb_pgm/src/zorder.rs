@@ -0,0 +1,430 @@
+//! Multi-dimensional point index over Morton (Z-order) keys
+//! 基于 Morton（Z 序）键的多维点索引
+//!
+//! Points are interleaved into one integer key and indexed by `PgmData`. A box
+//! query starts from the Z-range `[z(lo), z(hi)]` and splits it at its highest
+//! differing bit with LITMAX / BIGMIN, keeping only sub-ranges that hold keys.
+//! Each sub-range is located with a learned lookup; aligned cells are emitted
+//! whole, small ranges are scanned with BIGMIN jumps over out-of-box runs.
+//! 点坐标交织为单个整数键，由 `PgmData` 索引。盒查询从 Z 区间
+//! `[z(lo), z(hi)]` 出发，按最高差异位用 LITMAX / BIGMIN 拆分，只保留含键
+//! 的子区间。每个子区间用学习型查找定位；对齐单元整体输出，小区间扫描时用
+//! BIGMIN 跳过盒外片段。
+
+use alloc::{vec, vec::Vec};
+use core::fmt::Debug;
+
+use crate::{Key, PgmData, consts::ZORDER_SCAN_LEN, set::lower_bound_from};
+
+/// Point with a Morton (Z-order) encoding
+/// 具有 Morton（Z 序）编码的点
+///
+/// Coordinate `d` occupies key bits `d, d + DIMS, d + 2 * DIMS, ...`.
+/// 坐标 `d` 占用键的第 `d, d + DIMS, d + 2 * DIMS, ...` 位。
+pub trait ZPoint: Copy + Debug {
+  /// Interleaved key type
+  /// 交织键类型
+  type Key: Key + Into<u128> + TryFrom<u128>;
+
+  /// Number of dimensions
+  /// 维度数
+  const DIMS: u32;
+
+  /// Interleave coordinates into a Z-order key
+  /// 将坐标交织为 Z 序键
+  fn to_z(self) -> Self::Key;
+
+  /// De-interleave a Z-order key into coordinates
+  /// 将 Z 序键还原为坐标
+  fn from_z(z: Self::Key) -> Self;
+}
+
+/// Spread the 32 bits of `x` to the even bits of a u64
+/// 将 `x` 的 32 位分散到 u64 的偶数位
+#[inline]
+fn spread2(x: u32) -> u64 {
+  let mut x = x as u64;
+  x = (x | x << 16) & 0x0000_FFFF_0000_FFFF;
+  x = (x | x << 8) & 0x00FF_00FF_00FF_00FF;
+  x = (x | x << 4) & 0x0F0F_0F0F_0F0F_0F0F;
+  x = (x | x << 2) & 0x3333_3333_3333_3333;
+  (x | x << 1) & 0x5555_5555_5555_5555
+}
+
+/// Inverse of `spread2`
+/// `spread2` 的逆运算
+#[inline]
+fn compact2(z: u64) -> u32 {
+  let mut x = z & 0x5555_5555_5555_5555;
+  x = (x | x >> 1) & 0x3333_3333_3333_3333;
+  x = (x | x >> 2) & 0x0F0F_0F0F_0F0F_0F0F;
+  x = (x | x >> 4) & 0x00FF_00FF_00FF_00FF;
+  x = (x | x >> 8) & 0x0000_FFFF_0000_FFFF;
+  (x | x >> 16) as u32
+}
+
+/// Spread the low 21 bits of `x` to every third bit of a u64
+/// 将 `x` 的低 21 位分散到 u64 的每第三位
+#[inline]
+fn spread3(x: u64) -> u64 {
+  let mut x = x & 0x1F_FFFF;
+  x = (x | x << 32) & 0x001F_0000_0000_FFFF;
+  x = (x | x << 16) & 0x001F_0000_FF00_00FF;
+  x = (x | x << 8) & 0x100F_00F0_0F00_F00F;
+  x = (x | x << 4) & 0x10C3_0C30_C30C_30C3;
+  (x | x << 2) & 0x1249_2492_4924_9249
+}
+
+/// Inverse of `spread3`
+/// `spread3` 的逆运算
+#[inline]
+fn compact3(z: u64) -> u64 {
+  let mut x = z & 0x1249_2492_4924_9249;
+  x = (x | x >> 2) & 0x10C3_0C30_C30C_30C3;
+  x = (x | x >> 4) & 0x100F_00F0_0F00_F00F;
+  x = (x | x >> 8) & 0x001F_0000_FF00_00FF;
+  x = (x | x >> 16) & 0x001F_0000_0000_FFFF;
+  (x | x >> 32) & 0x1F_FFFF
+}
+
+/// Spread 32 bits to every third bit of a u128 (two 16-bit halves)
+/// 将 32 位分散到 u128 的每第三位（分两个 16 位半部）
+#[inline]
+fn spread3_wide(x: u32) -> u128 {
+  spread3((x & 0xFFFF) as u64) as u128 | (spread3((x >> 16) as u64) as u128) << 48
+}
+
+/// Inverse of `spread3_wide`
+/// `spread3_wide` 的逆运算
+#[inline]
+fn compact3_wide(z: u128) -> u32 {
+  (compact3(z as u64) | compact3((z >> 48) as u64) << 16) as u32
+}
+
+impl ZPoint for [u32; 2] {
+  type Key = u64;
+  const DIMS: u32 = 2;
+
+  #[inline]
+  fn to_z(self) -> u64 {
+    spread2(self[0]) | spread2(self[1]) << 1
+  }
+
+  #[inline]
+  fn from_z(z: u64) -> Self {
+    [compact2(z), compact2(z >> 1)]
+  }
+}
+
+impl ZPoint for [u64; 2] {
+  type Key = u128;
+  const DIMS: u32 = 2;
+
+  #[inline]
+  fn to_z(self) -> u128 {
+    let half = |v: u64| spread2(v as u32) as u128 | (spread2((v >> 32) as u32) as u128) << 64;
+    half(self[0]) | half(self[1]) << 1
+  }
+
+  #[inline]
+  fn from_z(z: u128) -> Self {
+    let half = |z: u128| compact2(z as u64) as u64 | (compact2((z >> 64) as u64) as u64) << 32;
+    [half(z), half(z >> 1)]
+  }
+}
+
+impl ZPoint for [u16; 3] {
+  type Key = u64;
+  const DIMS: u32 = 3;
+
+  #[inline]
+  fn to_z(self) -> u64 {
+    spread3(self[0] as u64) | spread3(self[1] as u64) << 1 | spread3(self[2] as u64) << 2
+  }
+
+  #[inline]
+  fn from_z(z: u64) -> Self {
+    [
+      compact3(z) as u16,
+      compact3(z >> 1) as u16,
+      compact3(z >> 2) as u16,
+    ]
+  }
+}
+
+impl ZPoint for [u32; 3] {
+  type Key = u128;
+  const DIMS: u32 = 3;
+
+  #[inline]
+  fn to_z(self) -> u128 {
+    spread3_wide(self[0]) | spread3_wide(self[1]) << 1 | spread3_wide(self[2]) << 2
+  }
+
+  #[inline]
+  fn from_z(z: u128) -> Self {
+    [
+      compact3_wide(z),
+      compact3_wide(z >> 1),
+      compact3_wide(z >> 2),
+    ]
+  }
+}
+
+/// Narrow a code known to fit the key width
+/// 收窄已知不超过键宽度的编码
+#[inline]
+fn narrow<K: TryFrom<u128>>(v: u128) -> K {
+  match K::try_from(v) {
+    Ok(k) => k,
+    Err(_) => unreachable!("z code exceeds key width"),
+  }
+}
+
+/// Axis-aligned box in Z space
+/// Z 空间中的轴对齐盒
+#[derive(Clone, Copy, Debug)]
+struct ZBox {
+  lo: u128,
+  hi: u128,
+  dims: u32,
+  /// Bits of dimension 0 (shift left by `d` for dimension `d`)
+  /// 第 0 维的位（左移 `d` 得到第 `d` 维）
+  mask0: u128,
+}
+
+impl ZBox {
+  /// Box from two corners, in any order per dimension
+  /// 由两个角点构建盒，各维顺序任意
+  fn new(a: u128, b: u128, dims: u32) -> Self {
+    let mut mask0 = 0u128;
+    let mut i = 0;
+    while i < 128 {
+      mask0 |= 1 << i;
+      i += dims;
+    }
+    let (mut lo, mut hi) = (0, 0);
+    for d in 0..dims {
+      let m = mask0 << d;
+      lo |= (a & m).min(b & m);
+      hi |= (a & m).max(b & m);
+    }
+    Self {
+      lo,
+      hi,
+      dims,
+      mask0,
+    }
+  }
+
+  #[inline]
+  fn with(self, lo: u128, hi: u128) -> Self {
+    Self { lo, hi, ..self }
+  }
+
+  /// Masked per-dimension compare: interleaved bits order like the coordinate
+  /// 按维掩码比较：交织位的顺序与坐标一致
+  #[inline]
+  fn contains(&self, z: u128) -> bool {
+    (0..self.dims).all(|d| {
+      let m = self.mask0 << d;
+      let v = z & m;
+      v >= self.lo & m && v <= self.hi & m
+    })
+  }
+
+  /// Bits of the same dimension as `bit`, strictly below it
+  /// 与 `bit` 同维且低于它的位
+  #[inline]
+  fn below(&self, bit: u32) -> u128 {
+    (self.mask0 << (bit % self.dims)) & ((1u128 << bit) - 1)
+  }
+
+  /// Set `bit` to 1 and lower bits of its dimension to 0 (`1000...`)
+  /// 将 `bit` 置 1，其同维低位清 0（`1000...`）
+  #[inline]
+  fn load_10(&self, v: u128, bit: u32) -> u128 {
+    (v & !self.below(bit)) | 1 << bit
+  }
+
+  /// Set `bit` to 0 and lower bits of its dimension to 1 (`0111...`)
+  /// 将 `bit` 清 0，其同维低位置 1（`0111...`）
+  #[inline]
+  fn load_01(&self, v: u128, bit: u32) -> u128 {
+    (v & !(1 << bit)) | self.below(bit)
+  }
+
+  /// Highest bit where `z`, `lo` and `hi` are not all equal
+  /// `z`、`lo`、`hi` 不全相同的最高位
+  #[inline]
+  fn top_bit(&self, z: u128) -> Option<u32> {
+    let diff = (z ^ self.lo) | (self.lo ^ self.hi);
+    (diff != 0).then(|| 127 - diff.leading_zeros())
+  }
+
+  /// Smallest in-box code >= `z` (requires `lo < z <= hi`)
+  /// 盒内 >= `z` 的最小编码（要求 `lo < z <= hi`）
+  fn bigmin(&self, z: u128) -> u128 {
+    if self.contains(z) {
+      return z;
+    }
+    let (mut min, mut max, mut out) = (self.lo, self.hi, self.hi);
+    let Some(top) = self.top_bit(z) else {
+      return z;
+    };
+    for bit in (0..=top).rev() {
+      let t = |v: u128| (v >> bit) & 1;
+      match (t(z), t(min), t(max)) {
+        (0, 0, 1) => {
+          out = self.load_10(min, bit);
+          max = self.load_01(max, bit);
+        }
+        (0, 1, 1) => return min,
+        (1, 0, 0) => return out,
+        (1, 0, 1) => min = self.load_10(min, bit),
+        _ => {}
+      }
+    }
+    out
+  }
+
+  /// Largest in-box code < `z` (requires `lo < z <= hi`)
+  /// 盒内 < `z` 的最大编码（要求 `lo < z <= hi`）
+  fn litmax(&self, z: u128) -> u128 {
+    let (mut min, mut max, mut out) = (self.lo, self.hi, self.lo);
+    let Some(top) = self.top_bit(z) else {
+      return z;
+    };
+    for bit in (0..=top).rev() {
+      let t = |v: u128| (v >> bit) & 1;
+      match (t(z), t(min), t(max)) {
+        (0, 0, 1) => max = self.load_01(max, bit),
+        (0, 1, 1) => return out,
+        (1, 0, 0) => return max,
+        (1, 0, 1) => {
+          out = self.load_01(max, bit);
+          min = self.load_10(min, bit);
+        }
+        _ => {}
+      }
+    }
+    out
+  }
+}
+
+/// Smallest code inside the box `[zmin, zmax]` that is >= `z`
+/// 盒 `[zmin, zmax]` 内 >= `z` 的最小编码
+///
+/// `zmin` / `zmax` are the codes of the box corners; requires `zmin < z <= zmax`.
+/// `zmin` / `zmax` 为盒角点编码；要求 `zmin < z <= zmax`。
+#[must_use]
+pub fn bigmin<P: ZPoint>(z: P::Key, zmin: P::Key, zmax: P::Key) -> P::Key {
+  let b = ZBox::new(zmin.into(), zmax.into(), P::DIMS);
+  narrow(b.bigmin(z.into()))
+}
+
+/// Largest code inside the box `[zmin, zmax]` that is < `z`
+/// 盒 `[zmin, zmax]` 内 < `z` 的最大编码
+///
+/// `zmin` / `zmax` are the codes of the box corners; requires `zmin < z <= zmax`.
+/// `zmin` / `zmax` 为盒角点编码；要求 `zmin < z <= zmax`。
+#[must_use]
+pub fn litmax<P: ZPoint>(z: P::Key, zmin: P::Key, zmax: P::Key) -> P::Key {
+  let b = ZBox::new(zmin.into(), zmax.into(), P::DIMS);
+  narrow(b.litmax(z.into()))
+}
+
+/// Point index over Z-order keys
+/// 基于 Z 序键的点索引
+#[derive(Clone, Debug)]
+pub struct ZIndex<P: ZPoint> {
+  pub data: PgmData<P::Key>,
+}
+
+impl<P: ZPoint> ZIndex<P> {
+  /// Build from points in any order (duplicates kept)
+  /// 从任意顺序的点构建（保留重复点）
+  pub fn new(points: &[P], epsilon: usize) -> Self {
+    let mut keys: Vec<P::Key> = points.iter().map(|p| p.to_z()).collect();
+    keys.sort_unstable();
+    Self {
+      data: PgmData::new(&keys, epsilon),
+    }
+  }
+
+  /// Number of points
+  /// 点数
+  #[inline]
+  #[must_use]
+  pub fn len(&self) -> usize {
+    self.data.sorted.len()
+  }
+
+  /// Whether the index is empty
+  /// 索引是否为空
+  #[inline]
+  #[must_use]
+  pub fn is_empty(&self) -> bool {
+    self.data.sorted.is_empty()
+  }
+
+  /// Points inside the box spanned by corners `a` and `b` (inclusive), in Z order
+  /// 由角点 `a`、`b` 张成的盒内（含边界）的点，按 Z 序返回
+  #[must_use]
+  pub fn query(&self, a: P, b: P) -> Vec<P> {
+    let mut out = vec![];
+    let sorted = self.data.sorted();
+    if sorted.is_empty() {
+      return out;
+    }
+    let bx = ZBox::new(a.to_z().into(), b.to_z().into(), P::DIMS);
+    // Pop order is ascending, so lookups only move forward
+    // 出栈顺序为升序，查找位置只会前移
+    let mut stack = vec![(bx.lo, bx.hi)];
+    let mut from = 0;
+    while let Some((lo, hi)) = stack.pop() {
+      let start = lower_bound_from(&self.data.pgm, sorted, narrow(lo), from);
+      // End is the learned lower bound of the code after `hi`, or the end of
+      // the keys when `hi` is the widest code
+      // 终点为 `hi` 之后编码的学习型下界；`hi` 为最大编码时取键的末尾
+      let end = match hi.checked_add(1).and_then(|n| P::Key::try_from(n).ok()) {
+        Some(next) => lower_bound_from(&self.data.pgm, sorted, next, start),
+        None => sorted.len(),
+      };
+      from = start;
+      if start == end {
+        continue;
+      }
+      let span = lo ^ hi;
+      let low = u128::MAX >> span.leading_zeros();
+      if span == 0 || (lo & low == 0 && hi & low == low) {
+        // Aligned cell: every code in range is inside the box
+        // 对齐单元：区间内所有编码都在盒内
+        out.extend(sorted[start..end].iter().map(|&k| P::from_z(k)));
+        from = end;
+      } else if end - start <= ZORDER_SCAN_LEN {
+        let sub = bx.with(lo, hi);
+        let mut i = start;
+        while i < end {
+          let z: u128 = sorted[i].into();
+          if sub.contains(z) {
+            out.push(P::from_z(sorted[i]));
+            i += 1;
+          } else {
+            let next: P::Key = narrow(sub.bigmin(z));
+            i += sorted[i..end].partition_point(|&k| k < next);
+          }
+        }
+        from = end;
+      } else {
+        let sub = bx.with(lo, hi);
+        // First code of the upper half: shared prefix, then 1, then zeros
+        // 上半部的首个编码：公共前缀，随后为 1，其余为 0
+        let mid = hi & !(low >> 1);
+        stack.push((sub.bigmin(mid), hi));
+        stack.push((lo, sub.litmax(mid)));
+      }
+    }
+    out
+  }
+}
@@ -0,0 +1,170 @@
+//! Tests for the SSTable block index
+//! SSTable 块索引测试
+
+use std::ops::{Bound, RangeBounds};
+
+use aok::{OK, Void};
+use jdb_pgm::BlockIndex;
+use log::trace;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+#[static_init::constructor(0)]
+extern "C" fn _log_init() {
+  log_init::init();
+}
+
+/// Random table: strictly increasing first keys, variable block sizes
+/// 随机表：首键严格递增，块大小可变
+fn gen_blocks(rng: &mut StdRng, n: usize) -> (Vec<(u64, u64)>, u64) {
+  let (mut key, mut off) = (1_000u64, 0u64);
+  let blocks = (0..n)
+    .map(|_| {
+      let b = (key, off);
+      key += rng.random_range(1..5_000);
+      off += rng.random_range(512..16_384);
+      b
+    })
+    .collect();
+  (blocks, off)
+}
+
+fn brute_find(blocks: &[(u64, u64)], key: u64) -> Option<usize> {
+  blocks.iter().rposition(|b| b.0 <= key)
+}
+
+/// Blocks whose key span `[first_i, first_{i+1})` meets `range`
+/// 键区间 `[first_i, first_{i+1})` 与 `range` 相交的块
+fn brute_range(blocks: &[(u64, u64)], range: (Bound<u64>, Bound<u64>)) -> Vec<usize> {
+  (0..blocks.len())
+    .filter(|&i| {
+      let lo = blocks[i].0;
+      let hi = blocks.get(i + 1).map_or(u64::MAX, |b| b.0 - 1);
+      let above = match range.start_bound() {
+        Bound::Included(&s) => hi >= s,
+        Bound::Excluded(&s) => hi > s,
+        Bound::Unbounded => true,
+      };
+      let below = match range.end_bound() {
+        Bound::Included(&e) => lo <= e,
+        Bound::Excluded(&e) => lo < e,
+        Bound::Unbounded => true,
+      };
+      above && below
+    })
+    .collect()
+}
+
+#[test]
+fn test_find_block() -> Void {
+  let mut rng = StdRng::seed_from_u64(23);
+  let (blocks, end) = gen_blocks(&mut rng, 5_000);
+  let idx = BlockIndex::new(&blocks, end, 8);
+  assert_eq!(idx.len(), blocks.len());
+
+  assert_eq!(idx.find_block(0), None);
+  assert_eq!(idx.find_block(999), None);
+  let last = blocks.len() - 1;
+  assert_eq!(
+    idx.find_block(u64::MAX),
+    Some((last, blocks[last].1, end - blocks[last].1))
+  );
+
+  for _ in 0..20_000 {
+    let key = rng.random_range(0..blocks[last].0 + 10_000);
+    let got = idx.find_block(key);
+    assert_eq!(got.map(|b| b.0), brute_find(&blocks, key), "key={key}");
+    if let Some((no, off, len)) = got {
+      assert_eq!(off, blocks[no].1);
+      assert_eq!(off + len, blocks.get(no + 1).map_or(end, |b| b.1));
+    }
+  }
+  for (no, &(first, _)) in blocks.iter().enumerate() {
+    assert_eq!(idx.find_block(first).unwrap().0, no);
+  }
+  trace!("find_block passed");
+  OK
+}
+
+#[test]
+fn test_block_range() -> Void {
+  let mut rng = StdRng::seed_from_u64(29);
+  let (blocks, end) = gen_blocks(&mut rng, 2_000);
+  let idx = BlockIndex::new(&blocks, end, 8);
+  let max = blocks[blocks.len() - 1].0 + 10_000;
+
+  let bound = |rng: &mut StdRng| match rng.random_range(0..4) {
+    0 => Bound::Unbounded,
+    1 => Bound::Excluded(rng.random_range(0..max)),
+    // Hit first keys exactly now and then
+    // 不时恰好命中首键
+    2 => Bound::Included(blocks[rng.random_range(0..blocks.len())].0),
+    _ => Bound::Included(rng.random_range(0..max)),
+  };
+  for _ in 0..5_000 {
+    let range = (bound(&mut rng), bound(&mut rng));
+    let got = idx.block_range(range);
+    let expect = brute_range(&blocks, range);
+    assert_eq!(got.clone().collect::<Vec<_>>(), expect, "{range:?}");
+
+    let bytes = idx.byte_range(got.clone());
+    let expect_bytes: u64 = got.map(|no| idx.block(no).1).sum();
+    assert_eq!(bytes.end - bytes.start, expect_bytes);
+  }
+  assert_eq!(idx.block_range(..), 0..blocks.len());
+  assert_eq!(idx.byte_range(idx.block_range(..)), 0..end);
+  OK
+}
+
+#[test]
+fn test_dump_load() -> Void {
+  let mut rng = StdRng::seed_from_u64(31);
+  let (blocks, end) = gen_blocks(&mut rng, 3_000);
+  let idx = BlockIndex::new(&blocks, end, 16);
+  let bytes = idx.dump();
+  assert_eq!(bytes.len(), 14 + blocks.len() * 8 + (blocks.len() + 1) * 8);
+
+  let loaded = BlockIndex::<u64>::load(&bytes)?;
+  assert_eq!(loaded.first_keys, idx.first_keys);
+  assert_eq!(loaded.offsets, idx.offsets);
+  assert_eq!(loaded.pgm.epsilon, 16);
+  for _ in 0..5_000 {
+    let key = rng.random();
+    assert_eq!(loaded.find_block(key), idx.find_block(key));
+  }
+
+  // Narrower keys round-trip too
+  // 更窄的键同样可往返
+  let small: Vec<(u32, u64)> = (0..100u32).map(|i| (i * 10, u64::from(i) * 100)).collect();
+  let idx32 = BlockIndex::new(&small, 10_000, 4);
+  let loaded32 = BlockIndex::<u32>::load(&idx32.dump())?;
+  assert_eq!(loaded32.find_block(55), Some((5, 500, 100)));
+
+  // Rejects corrupt input
+  // 拒绝损坏的输入
+  assert!(BlockIndex::<u64>::load(&bytes[..10]).is_err());
+  assert!(BlockIndex::<u64>::load(&bytes[..bytes.len() - 1]).is_err());
+  assert!(BlockIndex::<u32>::load(&bytes).is_err());
+  let mut bad = bytes.clone();
+  bad[0] = b'X';
+  assert!(BlockIndex::<u64>::load(&bad).is_err());
+  let mut bad = bytes.clone();
+  bad[14..22].copy_from_slice(&u64::MAX.to_le_bytes());
+  assert!(BlockIndex::<u64>::load(&bad).is_err());
+  let mut bad = bytes.clone();
+  bad[10..14].copy_from_slice(&u32::MAX.to_le_bytes());
+  assert!(BlockIndex::<u64>::load(&bad).is_err());
+
+  let empty = BlockIndex::<u64>::new(&[], 0, 16);
+  let loaded = BlockIndex::<u64>::load(&empty.dump())?;
+  assert!(loaded.is_empty());
+  assert_eq!(loaded.find_block(5), None);
+  assert_eq!(loaded.block_range(..), 0..0);
+  OK
+}
+
+#[test]
+#[should_panic(expected = "epsilon exceeds u32")]
+fn test_dump_epsilon_overflow() {
+  let blocks = [(1u64, 0u64), (5, 100)];
+  let _ = BlockIndex::new(&blocks, 200, u32::MAX as usize + 1).dump();
+}
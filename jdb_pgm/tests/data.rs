@@ -191,3 +191,121 @@ fn test_random() -> Void {
   trace!("random passed, n={}", sorted.len());
   OK
 }
+
+/// Brute-force k nearest window: ties go to the smaller key
+/// 暴力求最近 k 个键窗口：距离相等时取较小的键
+fn brute_k_nearest(sorted: &[i64], key: i64, k: usize) -> Vec<i64> {
+  let mut v = sorted.to_vec();
+  v.sort_by_key(|&x| (x.abs_diff(key), x));
+  v.truncate(k);
+  v.sort_unstable();
+  v
+}
+
+#[test]
+fn test_nearest() -> Void {
+  use rand::{Rng, SeedableRng, rngs::StdRng};
+
+  let mut rng = StdRng::seed_from_u64(21);
+  let mut sorted: Vec<i64> = (0..5_000)
+    .map(|_| rng.random_range(-50_000..50_000))
+    .collect();
+  // Duplicates and an equidistant pair around 100
+  // 重复键以及 100 两侧等距的一对键
+  sorted.extend([7, 7, 7, 90, 110]);
+  sorted.sort_unstable();
+  sorted.retain(|&x| !(81..120).contains(&x) || x == 90 || x == 110);
+  sorted.dedup_by(|a, b| *a == *b && (81..120).contains(a));
+  let idx = PgmData::new(&sorted, 16);
+
+  for key in (0..2_000)
+    .map(|_| rng.random_range(-60_000..60_000))
+    .chain([
+      i64::MIN,
+      i64::MAX,
+      7,
+      100,
+      sorted[0],
+      sorted[sorted.len() - 1],
+    ])
+  {
+    for k in [0, 1, 2, 5, 33] {
+      let r = idx.k_nearest(key, k);
+      assert_eq!(
+        sorted[r].to_vec(),
+        brute_k_nearest(&sorted, key, k),
+        "key={key} k={k}"
+      );
+    }
+    let pos = idx.nearest(key).unwrap();
+    let expect = brute_k_nearest(&sorted, key, 1)[0];
+    assert_eq!(sorted[pos], expect, "key={key}");
+    assert!(pos == 0 || sorted[pos - 1] != expect, "first duplicate");
+  }
+
+  // Equidistant: 90 and 110 around 100, the smaller key wins
+  // 等距：100 两侧的 90 与 110，取较小的键
+  assert_eq!(sorted[idx.nearest(100).unwrap()], 90);
+  assert_eq!(sorted[idx.k_nearest(100, 2)], [90, 110]);
+
+  let all = idx.k_nearest(0, sorted.len() + 10);
+  assert_eq!(all, 0..sorted.len());
+
+  let empty = PgmData::<u64>::new(&[], 16);
+  assert_eq!(empty.nearest(5), None);
+  assert_eq!(empty.k_nearest(5, 3), 0..0);
+
+  // Exact distance near u64::MAX where f64 would round
+  // 在 u64::MAX 附近验证精确距离（f64 会舍入）
+  let big = [u64::MAX - 3, u64::MAX - 1];
+  let idx = PgmData::new(&big, 16);
+  assert_eq!(idx.nearest(u64::MAX), Some(1));
+  assert_eq!(idx.nearest(u64::MAX - 2), Some(0));
+  assert_eq!(idx.k_nearest(0, 1), 0..1);
+
+  trace!("nearest passed");
+  OK
+}
+
+#[test]
+fn test_storage() -> Void {
+  use std::sync::Arc;
+
+  use jdb_pgm::PgmRef;
+
+  let sorted: Vec<u64> = (0..20_000u64).map(|i| i * 3 + i % 7).collect();
+  let owned = PgmData::new(&sorted, 16);
+  let borrowed = PgmRef::borrowed(&sorted, 16);
+  let shared: Arc<[u64]> = sorted.clone().into();
+  let arc = PgmData::from_storage(Arc::clone(&shared), 16);
+
+  // No copy: the index reads the caller's buffer
+  // 无复制：索引直接读取调用方的缓冲区
+  assert!(std::ptr::eq(borrowed.sorted(), sorted.as_slice()));
+  assert!(std::ptr::eq(arc.sorted(), &*shared));
+
+  for key in (0..61_000).step_by(13) {
+    let expect = owned.get(key);
+    assert_eq!(borrowed.get(key), expect, "key={key}");
+    assert_eq!(arc.get(key), expect, "key={key}");
+    assert_eq!(arc.nearest(key), owned.nearest(key));
+  }
+  assert_eq!(borrowed.memory_usage(), owned.memory_usage());
+
+  // Set operations mix storage types
+  // 集合运算可混用不同存储
+  let other: Vec<u64> = (0..5_000u64).map(|i| i * 11).collect();
+  let other = PgmRef::borrowed(&other, 16);
+  assert_eq!(
+    arc.intersect(&other).collect::<Vec<_>>(),
+    owned.intersect(&other).collect::<Vec<_>>()
+  );
+  assert_eq!(
+    borrowed.union(&arc).collect::<Vec<_>>(),
+    owned.union(&owned).collect::<Vec<_>>()
+  );
+
+  let back = arc.into_storage();
+  assert_eq!(Arc::strong_count(&back), 2);
+  OK
+}
@@ -2,7 +2,7 @@
 //! Pgm 测试（不持有数据）
 
 use aok::{OK, Void};
-use jdb_pgm::Pgm;
+use jdb_pgm::{Layout, Pgm};
 use log::trace;
 
 #[static_init::constructor(0)]
@@ -235,3 +235,294 @@ fn test_random() -> Void {
   trace!("random passed, n={}", sorted.len());
   OK
 }
+
+#[test]
+fn test_layouts() -> Void {
+  use rand::{Rng, SeedableRng, rngs::StdRng};
+
+  let mut rng = StdRng::seed_from_u64(777);
+  let mut random: Vec<u64> = (0..20_000)
+    .map(|_| rng.random_range(0..10_000_000))
+    .collect();
+  random.sort();
+  let quadratic: Vec<u64> = (0..3000u64).map(|i| i * i).collect();
+  let dups = vec![1u64, 1, 1, 2, 2, 3, 3, 3, 3, 9, 9, 9, 9, 9, 9, 9];
+
+  for sorted in [&random, &quadratic, &dups] {
+    for layout in [Layout::Lut, Layout::Sorted, Layout::Eytzinger] {
+      for eps in [1usize, 4, 32] {
+        let pgm = Pgm::new_with_layout(sorted, eps, layout);
+        for &k in sorted.iter() {
+          let pos = search(&pgm, sorted, k);
+          assert_eq!(
+            pos.map(|p| sorted[p]),
+            Some(k),
+            "{layout:?} eps={eps} key={k}"
+          );
+        }
+        assert_eq!(search(&pgm, sorted, sorted[sorted.len() - 1] + 1), None);
+
+        // Dense layouts drop the segments but answer the sketch APIs alike
+        // 紧凑布局不保存段，但概要 API 的结果相同
+        let lut = Pgm::new(sorted, eps);
+        if layout != Layout::Lut {
+          assert!(pgm.segments.is_empty());
+          assert!(pgm.mem_usage() < lut.mem_usage() + lut.segment_count() * 16);
+        }
+        assert_eq!(pgm.segment_count(), lut.segment_count());
+        for i in (0..sorted.len()).step_by(7) {
+          let (a, b) = (
+            pgm.find_segment_for_index(i).unwrap(),
+            lut.find_segment_for_index(i).unwrap(),
+          );
+          assert_eq!(
+            (a.min_key, a.max_key, a.start_idx, a.end_idx),
+            (b.min_key, b.max_key, b.start_idx, b.end_idx),
+            "{layout:?} eps={eps} i={i}"
+          );
+        }
+        for &k in sorted.iter().step_by(5) {
+          for k in [k.saturating_sub(1), k, k + 1] {
+            assert_eq!(
+              pgm.cdf(k).to_bits(),
+              lut.cdf(k).to_bits(),
+              "{layout:?} eps={eps} key={k}"
+            );
+            assert_eq!(pgm.estimate_count(..=k), lut.estimate_count(..=k));
+            assert_eq!(pgm.estimate_count(k..), lut.estimate_count(k..));
+          }
+        }
+        assert_eq!(pgm.partition_points(9), lut.partition_points(9));
+      }
+    }
+  }
+
+  // Duplicate runs longer than a segment split into segments sharing keys
+  // 长于一个段的重复键会拆成键相同的多个段
+  let mut runs = vec![0u64];
+  runs.extend([5u64; 100]);
+  runs.push(10);
+  let mut heavy: Vec<u64> = (0..5000).map(|_| rng.random_range(0..40) * 7).collect();
+  heavy.sort();
+  for sorted in [&runs, &heavy] {
+    for layout in [Layout::Lut, Layout::Sorted, Layout::Eytzinger] {
+      for eps in [1usize, 2, 4, 32] {
+        let pgm = Pgm::new_with_layout(sorted, eps, layout);
+        for k in 0..=sorted[sorted.len() - 1] + 1 {
+          assert_eq!(
+            pgm.find_key(k, |i| sorted.get(i).copied()),
+            sorted.partition_point(|&x| x < k),
+            "{layout:?} eps={eps} key={k}"
+          );
+        }
+      }
+    }
+  }
+
+  trace!("layouts passed");
+  OK
+}
+
+#[test]
+fn test_cdf_quantile() -> Void {
+  use rand::{Rng, SeedableRng, rngs::StdRng};
+
+  let mut rng = StdRng::seed_from_u64(2024);
+  let mut sorted: Vec<u64> = (0..50_000)
+    .map(|_| rng.random_range(0..1_000_000_000))
+    .collect();
+  sorted.sort();
+  let n = sorted.len();
+  let eps = 32;
+  let pgm = Pgm::new(&sorted, eps);
+
+  // cdf is within ε of the true rank
+  // cdf 与真实秩的误差不超过 ε
+  for _ in 0..1000 {
+    let k = rng.random_range(0..1_100_000_000u64);
+    let rank = sorted.partition_point(|&x| x < k);
+    let est = pgm.cdf(k) * n as f64;
+    assert!(
+      (est - rank as f64).abs() <= (eps + 1) as f64,
+      "key={k} rank={rank} est={est}"
+    );
+  }
+  assert_eq!(pgm.cdf(0), 0.0);
+  assert_eq!(pgm.cdf(u64::MAX), 1.0);
+
+  // quantile lands within ε of the target position
+  // quantile 落在目标位置 ε 范围内
+  for i in 0..=100 {
+    let p = i as f64 / 100.0;
+    let k = pgm.quantile(p).unwrap();
+    let target = ((p * n as f64) as usize).min(n - 1);
+    let lo = sorted.partition_point(|&x| x < k);
+    let hi = sorted.partition_point(|&x| x <= k);
+    let dist = if target < lo {
+      lo - target
+    } else {
+      target.saturating_sub(hi)
+    };
+    assert!(
+      dist <= eps + 1,
+      "p={p} key={k} target={target} range={lo}..{hi}"
+    );
+  }
+  assert_eq!(pgm.quantile(0.0), Some(sorted[0]));
+  assert_eq!(pgm.quantile(f64::NAN), Some(sorted[0]));
+  assert_eq!(Pgm::<u64>::new(&[], 8).quantile(0.5), None);
+
+  // Balanced partitions
+  // 均衡分区
+  let parts = 16;
+  let points = pgm.partition_points(parts);
+  assert_eq!(points.len(), parts - 1);
+  assert!(points.is_sorted());
+  let mut prev = 0;
+  for &b in points.iter().chain(std::iter::once(&u64::MAX)) {
+    let end = if b == u64::MAX {
+      n
+    } else {
+      sorted.partition_point(|&x| x < b)
+    };
+    let size = end - prev;
+    assert!(size.abs_diff(n / parts) <= 2 * eps + 2, "part size={size}");
+    prev = end;
+  }
+  assert!(pgm.partition_points(1).is_empty());
+
+  // Segments of one repeated key have no finite slope
+  // 只含一个重复键的段没有有限斜率
+  let dups = [1u64, 1, 1, 2, 2, 3, 3, 3, 3, 9, 9, 9, 9, 9, 9, 9];
+  for eps in [1usize, 4] {
+    let pgm = Pgm::new(&dups, eps);
+    for k in 0..=10 {
+      let rank = dups.partition_point(|&x| x < k) as f64;
+      let est = pgm.cdf(k) * dups.len() as f64;
+      assert!((est - rank).abs() <= (eps + 1) as f64, "eps={eps} key={k}");
+    }
+  }
+
+  trace!("cdf/quantile passed");
+  OK
+}
+
+/// Lower bound through `find_key` must match `partition_point`
+/// 经 `find_key` 得到的下界必须与 `partition_point` 一致
+fn check_lower_bound(pgm: &Pgm<u64>, sorted: &[u64], key: u64) {
+  let got = pgm.find_key(key, |i| sorted.get(i).copied());
+  assert_eq!(got, sorted.partition_point(|&k| k < key), "key={key}");
+}
+
+#[test]
+fn test_segment_error_bounds() -> Void {
+  use rand::{Rng, SeedableRng, rngs::StdRng};
+
+  let mut rng = StdRng::seed_from_u64(43);
+  let eps = 64;
+  let mut uniform: Vec<u64> = (0..100_000).map(|_| rng.random_range(0..1 << 40)).collect();
+  uniform.sort_unstable();
+  let mut dups: Vec<u64> = (0..100_000).map(|_| rng.random_range(0..5_000)).collect();
+  dups.sort_unstable();
+  let cases: Vec<(&str, Vec<u64>)> = vec![
+    ("seq", (0..100_000).collect()),
+    ("quadratic", (0..50_000u64).map(|i| i * i).collect()),
+    ("uniform", uniform),
+    ("dups", dups),
+  ];
+
+  for (name, sorted) in &cases {
+    let pgm = Pgm::new(sorted, eps);
+    let mut width = 0usize;
+    for seg in &pgm.segments {
+      assert!(
+        seg.err_lo as usize <= eps + 1,
+        "{name} err_lo={}",
+        seg.err_lo
+      );
+      assert!(
+        seg.err_hi as usize <= eps + 1,
+        "{name} err_hi={}",
+        seg.err_hi
+      );
+    }
+    for &k in sorted.iter().step_by(7) {
+      let r = pgm.predict_range(k);
+      width += r.len();
+      check_lower_bound(&pgm, sorted, k);
+      check_lower_bound(&pgm, sorted, k.wrapping_add(1));
+      check_lower_bound(&pgm, sorted, k.saturating_sub(1));
+    }
+    for _ in 0..5_000 {
+      check_lower_bound(&pgm, sorted, rng.random_range(0..1 << 41));
+    }
+    check_lower_bound(&pgm, sorted, 0);
+    check_lower_bound(&pgm, sorted, u64::MAX);
+
+    let avg = width as f64 / sorted.len().div_ceil(7) as f64;
+    trace!(
+      "{name}: avg window {avg:.1} (global 2ε+1 = {})",
+      2 * eps + 1
+    );
+    assert!(avg <= (2 * eps + 1) as f64, "{name} avg window {avg}");
+  }
+
+  // Linear data is predicted exactly, so the window collapses to one slot
+  // 线性数据预测精确，窗口收缩为一个位置
+  let pgm = Pgm::new(&cases[0].1, eps);
+  assert_eq!(pgm.predict_range(5_000).len(), 1);
+  OK
+}
+
+#[cfg(feature = "bitcode")]
+#[test]
+fn test_error_bounds_bitcode() -> Void {
+  let sorted: Vec<u64> = (0..10_000u64).map(|i| i * i / 7).collect();
+  let pgm = Pgm::new(&sorted, 32);
+  let decoded: Pgm<u64> = bitcode::decode(&bitcode::encode(&pgm))?;
+  for (a, b) in pgm.segments.iter().zip(&decoded.segments) {
+    assert_eq!((a.err_lo, a.err_hi), (b.err_lo, b.err_hi));
+  }
+  for &k in sorted.iter().step_by(13) {
+    assert_eq!(decoded.predict_range(k), pgm.predict_range(k));
+  }
+  OK
+}
+
+#[cfg(feature = "bitcode")]
+#[test]
+fn test_decode_v0_bitcode() -> Void {
+  // Written by the first released version: Pgm::new(&sorted, 16)
+  // 由首个发布版本写入：Pgm::new(&sorted, 16)
+  let bytes = include_bytes!("data/pgm_v0.bin");
+  let mut sorted: Vec<u64> = (0..2000u64).map(|i| i * i / 7 + (i % 5) * 3).collect();
+  sorted.sort();
+
+  assert!(bitcode::decode::<Pgm<u64>>(bytes).is_err());
+  let old: jdb_pgm::compat::PgmV0<u64> = bitcode::decode(bytes)?;
+  let pgm = Pgm::<u64>::decode_bitcode(bytes)?;
+  assert_eq!((pgm.epsilon, pgm.len), (16, sorted.len()));
+  assert_eq!(pgm.segments.len(), old.segments.len());
+  assert!(
+    pgm
+      .segments
+      .iter()
+      .all(|s| (s.err_lo, s.err_hi) == (16, 16))
+  );
+  for k in 0..=sorted[sorted.len() - 1] + 1 {
+    assert_eq!(
+      pgm.find_key(k, |i| sorted.get(i).copied()),
+      sorted.partition_point(|&x| x < k),
+      "key={k}"
+    );
+  }
+
+  // Current bytes still take the current path
+  // 当前编码仍走当前路径
+  let fresh = Pgm::new(&sorted, 16);
+  let decoded = Pgm::<u64>::decode_bitcode(&bitcode::encode(&fresh))?;
+  for (a, b) in fresh.segments.iter().zip(&decoded.segments) {
+    assert_eq!((a.err_lo, a.err_hi), (b.err_lo, b.err_hi));
+  }
+  OK
+}
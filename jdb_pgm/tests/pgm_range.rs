@@ -4,7 +4,7 @@
 use std::ops::Range;
 
 use aok::{OK, Void};
-use jdb_pgm::Pgm;
+use jdb_pgm::{Layout, Pgm};
 
 #[static_init::constructor(0)]
 extern "C" fn _log_init() {
@@ -160,3 +160,128 @@ fn test_range_all_same() -> Void {
   assert!(r.end <= 100);
   OK
 }
+
+/// True count of keys in range
+/// 范围内键的真实数量
+fn true_count<R: std::ops::RangeBounds<u64>>(sorted: &[u64], range: &R) -> usize {
+  sorted.iter().filter(|k| range.contains(k)).count()
+}
+
+/// Check the estimate brackets the true count
+/// 检查估计值的边界包含真实数量
+fn check_bounds<R: std::ops::RangeBounds<u64> + Clone + std::fmt::Debug>(
+  pgm: &Pgm<u64>,
+  sorted: &[u64],
+  range: R,
+) -> jdb_pgm::CountEstimate {
+  let truth = true_count(sorted, &range);
+  let est = pgm.estimate_count(range.clone());
+  assert!(
+    est.lower <= truth && truth <= est.upper,
+    "{range:?}: truth={truth} est={est:?}"
+  );
+  assert!(est.lower <= est.estimate && est.estimate <= est.upper);
+  est
+}
+
+fn check_estimate<R: std::ops::RangeBounds<u64> + Clone + std::fmt::Debug>(
+  pgm: &Pgm<u64>,
+  sorted: &[u64],
+  range: R,
+) {
+  let est = check_bounds(pgm, sorted, range.clone());
+  assert!(
+    est.upper - est.lower <= 4 * pgm.epsilon + 2,
+    "{range:?}: {est:?}"
+  );
+}
+
+#[test]
+fn test_estimate_count() -> Void {
+  use rand::{Rng, SeedableRng, rngs::StdRng};
+
+  let mut rng = StdRng::seed_from_u64(99);
+  let mut sorted: Vec<u64> = (0..20_000)
+    .map(|_| rng.random_range(1_000..5_000_000))
+    .collect();
+  sorted.sort();
+  sorted.dedup();
+
+  for &eps in &[4usize, 32, 128] {
+    let pgm = Pgm::new(&sorted, eps);
+    for _ in 0..500 {
+      let a = rng.random_range(0..5_100_000u64);
+      let b = rng.random_range(a..5_200_000u64);
+      check_estimate(&pgm, &sorted, a..b);
+      check_estimate(&pgm, &sorted, a..=b);
+      check_estimate(&pgm, &sorted, a..);
+      check_estimate(&pgm, &sorted, ..b);
+      check_estimate(
+        &pgm,
+        &sorted,
+        (std::ops::Bound::Excluded(a), std::ops::Bound::Included(b)),
+      );
+    }
+    // Keys taken from data hit both bound kinds exactly
+    // 取自数据的键精确命中两类边界
+    for _ in 0..200 {
+      let a = sorted[rng.random_range(0..sorted.len())];
+      let b = sorted[rng.random_range(0..sorted.len())].max(a);
+      check_estimate(&pgm, &sorted, a..=b);
+      check_estimate(&pgm, &sorted, a..b);
+    }
+  }
+
+  let pgm = Pgm::new(&sorted, 32);
+  assert_eq!(pgm.estimate_count(..).estimate, sorted.len());
+  let outside = pgm.estimate_count(..500);
+  assert_eq!((outside.lower, outside.upper), (0, 0));
+  let outside = pgm.estimate_count(6_000_000..);
+  assert_eq!((outside.lower, outside.upper), (0, 0));
+  let all = pgm.estimate_count(0..=u64::MAX);
+  assert_eq!(
+    (all.lower, all.estimate, all.upper),
+    (sorted.len(), sorted.len(), sorted.len())
+  );
+
+  let empty = Pgm::<u64>::new(&[], 32);
+  assert_eq!(empty.estimate_count(..), Default::default());
+  OK
+}
+
+#[test]
+fn test_estimate_count_duplicates() -> Void {
+  use rand::{Rng, SeedableRng, rngs::StdRng};
+
+  // Runs longer than a segment split into segments sharing the same key
+  // 长于一个段的重复键会拆成键相同的多个段
+  let mut runs = vec![0u64];
+  runs.extend([5u64; 100]);
+  runs.push(10);
+  let mut rng = StdRng::seed_from_u64(7);
+  let mut heavy: Vec<u64> = (0..5000).map(|_| rng.random_range(0..40) * 7).collect();
+  heavy.sort();
+
+  for sorted in [&runs, &heavy] {
+    let max = sorted[sorted.len() - 1] + 1;
+    for layout in [Layout::Lut, Layout::Sorted, Layout::Eytzinger] {
+      for eps in [1usize, 2, 4, 32] {
+        let pgm = Pgm::new_with_layout(sorted, eps, layout);
+        for a in 0..=max {
+          check_bounds(&pgm, sorted, ..=a);
+          check_bounds(&pgm, sorted, ..a);
+          check_bounds(&pgm, sorted, a..=a);
+          check_bounds(&pgm, sorted, a..);
+          check_bounds(&pgm, sorted, a..a + 3);
+        }
+      }
+    }
+  }
+
+  let pgm = Pgm::new(&runs, 2);
+  let est = pgm.estimate_count(..=5);
+  assert!(est.lower <= 101 && 101 <= est.upper, "{est:?}");
+  let est = pgm.estimate_count(5..=5);
+  assert!(est.lower <= 100 && 100 <= est.upper, "{est:?}");
+  OK
+}
@@ -0,0 +1,85 @@
+//! Tests for PgmRecords and Pgm::from_sorted_by
+//! PgmRecords 与 Pgm::from_sorted_by 测试
+
+#![cfg(feature = "data")]
+
+use aok::{OK, Void};
+use jdb_pgm::{Pgm, PgmRecords};
+use log::trace;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+#[static_init::constructor(0)]
+extern "C" fn _log_init() {
+  log_init::init();
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Record {
+  id: u64,
+  name: String,
+}
+
+fn gen_records(rng: &mut StdRng, n: usize) -> Vec<Record> {
+  let mut v: Vec<Record> = (0..n)
+    .map(|i| Record {
+      id: rng.random_range(0..(n as u64) * 4),
+      name: format!("r{i}"),
+    })
+    .collect();
+  v.sort_by_key(|r| r.id);
+  v
+}
+
+#[test]
+fn test_from_sorted_by() -> Void {
+  let mut rng = StdRng::seed_from_u64(37);
+  let records = gen_records(&mut rng, 50_000);
+  let ids: Vec<u64> = records.iter().map(|r| r.id).collect();
+  for eps in [4, 32] {
+    let by = Pgm::from_sorted_by(&records, |r| r.id, eps);
+    let plain = Pgm::new(&ids, eps);
+    assert_eq!(by.segments.len(), plain.segments.len());
+    for (a, b) in by.segments.iter().zip(&plain.segments) {
+      assert_eq!((a.min_key, a.max_key), (b.min_key, b.max_key));
+      assert_eq!((a.start_idx, a.end_idx), (b.start_idx, b.end_idx));
+      assert_eq!((a.slope, a.intercept), (b.slope, b.intercept));
+    }
+    assert_eq!(by.lut, plain.lut);
+    assert_eq!(by.len(), records.len());
+  }
+  let empty = Pgm::from_sorted_by(&[] as &[Record], |r| r.id, 16);
+  assert!(empty.is_empty());
+  OK
+}
+
+#[test]
+fn test_records_get_range() -> Void {
+  let mut rng = StdRng::seed_from_u64(41);
+  let records = gen_records(&mut rng, 20_000);
+  let idx = PgmRecords::new(records.clone(), |r: &Record| r.id, 16);
+  assert_eq!(idx.records().len(), records.len());
+
+  // Expected answers are slices cut by partition_point on the source
+  // 期望结果为在源数据上用 partition_point 截取的切片
+  let below = |k: u64| records.partition_point(|r| r.id < k);
+  let upto = |k: u64| records.partition_point(|r| r.id <= k);
+  for _ in 0..10_000 {
+    let key = rng.random_range(0..90_000);
+    let at = below(key);
+    let found = (at < records.len() && records[at].id == key).then_some(at);
+    assert_eq!(idx.position(key), found, "key={key}");
+    assert_eq!(idx.get(key), found.map(|i| &records[i]));
+
+    let hi = key + rng.random_range(0..200);
+    assert_eq!(idx.range(key..hi), &records[at..below(hi)]);
+    assert_eq!(idx.range(key..=hi), &records[at..upto(hi)]);
+    assert_eq!(idx.range(..hi), &records[..below(hi)]);
+    assert_eq!(idx.range(key..), &records[at..]);
+  }
+  assert_eq!(idx.range(..).len(), records.len());
+  let (lo, hi) = (10, 5);
+  assert!(idx.range(lo..hi).is_empty());
+  assert_eq!(idx.into_records(), records);
+  trace!("records get/range passed");
+  OK
+}
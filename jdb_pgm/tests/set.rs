@@ -0,0 +1,143 @@
+//! Tests for PgmData set operations
+//! PgmData 集合运算测试
+
+#![cfg(feature = "data")]
+
+use aok::{OK, Void};
+use jdb_pgm::PgmData;
+use log::trace;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+#[static_init::constructor(0)]
+extern "C" fn _log_init() {
+  log_init::init();
+}
+
+fn gen_sorted(rng: &mut StdRng, n: usize, max: u64) -> Vec<u64> {
+  let mut v: Vec<u64> = (0..n).map(|_| rng.random_range(0..max)).collect();
+  v.sort_unstable();
+  v
+}
+
+/// Reference merge implementations
+/// 参考归并实现
+fn merge_intersect(a: &[u64], b: &[u64]) -> Vec<(usize, usize)> {
+  let (mut i, mut j, mut out) = (0, 0, vec![]);
+  while i < a.len() && j < b.len() {
+    match a[i].cmp(&b[j]) {
+      std::cmp::Ordering::Less => i += 1,
+      std::cmp::Ordering::Greater => j += 1,
+      std::cmp::Ordering::Equal => {
+        out.push((i, j));
+        i += 1;
+        j += 1;
+      }
+    }
+  }
+  out
+}
+
+fn merge_union(a: &[u64], b: &[u64]) -> Vec<u64> {
+  let (mut i, mut j, mut out) = (0, 0, vec![]);
+  while i < a.len() || j < b.len() {
+    if j == b.len() || (i < a.len() && a[i] < b[j]) {
+      out.push(a[i]);
+      i += 1;
+    } else if i == a.len() || b[j] < a[i] {
+      out.push(b[j]);
+      j += 1;
+    } else {
+      out.push(a[i]);
+      i += 1;
+      j += 1;
+    }
+  }
+  out
+}
+
+fn merge_difference(a: &[u64], b: &[u64]) -> Vec<usize> {
+  let (mut i, mut j, mut out) = (0, 0, vec![]);
+  while i < a.len() {
+    if j == b.len() || a[i] < b[j] {
+      out.push(i);
+      i += 1;
+    } else {
+      if a[i] == b[j] {
+        i += 1;
+      }
+      j += 1;
+    }
+  }
+  out
+}
+
+fn check(a: &[u64], b: &[u64]) {
+  let pa = PgmData::new(a, 16);
+  let pb = PgmData::new(b, 16);
+  let msg = format!("len a={} b={}", a.len(), b.len());
+
+  assert_eq!(
+    pa.intersect(&pb).collect::<Vec<_>>(),
+    merge_intersect(a, b),
+    "intersect {msg}"
+  );
+  assert_eq!(
+    pa.union(&pb).collect::<Vec<_>>(),
+    merge_union(a, b),
+    "union {msg}"
+  );
+  assert_eq!(
+    pa.difference(&pb).collect::<Vec<_>>(),
+    merge_difference(a, b),
+    "difference {msg}"
+  );
+}
+
+#[test]
+fn test_set_sizes() -> Void {
+  let mut rng = StdRng::seed_from_u64(7);
+  // Covers merge mode (similar sizes) and both learned directions
+  // 覆盖归并模式（长度相近）及两个学习型方向
+  for &(na, nb) in &[
+    (0, 0),
+    (0, 100),
+    (100, 0),
+    (1, 1),
+    (1000, 1200),
+    (10, 10_000),
+    (10_000, 10),
+    (200, 50_000),
+    (50_000, 200),
+  ] {
+    let a = gen_sorted(&mut rng, na, 100_000);
+    let b = gen_sorted(&mut rng, nb, 100_000);
+    check(&a, &b);
+  }
+  trace!("set sizes passed");
+  OK
+}
+
+#[test]
+fn test_set_duplicates() -> Void {
+  let mut rng = StdRng::seed_from_u64(11);
+  for &(na, nb) in &[(50, 5000), (5000, 50), (300, 300)] {
+    let a = gen_sorted(&mut rng, na, 64);
+    let b = gen_sorted(&mut rng, nb, 64);
+    check(&a, &b);
+  }
+  OK
+}
+
+#[test]
+fn test_set_disjoint() -> Void {
+  let a: Vec<u64> = (0..100).collect();
+  let b: Vec<u64> = (1_000..100_000).collect();
+  check(&a, &b);
+  check(&b, &a);
+
+  let pa = PgmData::new(&a, 16);
+  let pb = PgmData::new(&b, 16);
+  assert_eq!(pa.intersect(&pb).count(), 0);
+  assert_eq!(pa.difference(&pb).count(), a.len());
+  OK
+}
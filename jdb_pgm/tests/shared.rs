@@ -0,0 +1,115 @@
+//! Tests for SharedPgm
+//! SharedPgm 测试
+
+#![cfg(feature = "shared")]
+
+use std::{
+  sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+  },
+  thread,
+};
+
+use aok::{OK, Void};
+use jdb_pgm::{PgmData, SharedPgm};
+use log::trace;
+
+#[static_init::constructor(0)]
+extern "C" fn _log_init() {
+  log_init::init();
+}
+
+#[test]
+fn test_publish() -> Void {
+  let v1: Vec<u64> = (0..1000).collect();
+  let shared = SharedPgm::new(PgmData::new(&v1, 16));
+
+  let old = shared.load();
+  assert_eq!(old.get(999), Some(999));
+
+  let v2: Vec<u64> = (0..2000).map(|i| i * 2).collect();
+  shared.publish(PgmData::new(&v2, 16));
+
+  // Old guard still sees the old version
+  // 旧守卫仍可见旧版本
+  assert_eq!(old.len(), 1000);
+  assert_eq!(old.get(999), Some(999));
+
+  let new = shared.load();
+  assert_eq!(new.len(), 2000);
+  assert_eq!(new.get(999), None);
+  assert_eq!(new.get(3998), Some(1999));
+  OK
+}
+
+#[test]
+fn test_reclaim() -> Void {
+  let v: Vec<u64> = (0..100).collect();
+  let shared = SharedPgm::new(PgmData::new(&v, 8));
+
+  let weak = Arc::downgrade(&shared.load().into_inner());
+  let guard = shared.load();
+  shared.publish(PgmData::new(&v[..50], 8));
+  assert!(weak.upgrade().is_some());
+
+  drop(guard);
+  assert!(weak.upgrade().is_none());
+  OK
+}
+
+#[test]
+fn test_concurrent_readers() -> Void {
+  let v: Vec<u64> = (0..10_000).collect();
+  let shared = Arc::new(SharedPgm::new(PgmData::new(&v, 32)));
+  let stop = Arc::new(AtomicBool::new(false));
+
+  let readers: Vec<_> = (0..4)
+    .map(|_| {
+      let shared = Arc::clone(&shared);
+      let stop = Arc::clone(&stop);
+      thread::spawn(move || {
+        let mut reads = 0usize;
+        while !stop.load(Ordering::Relaxed) {
+          let idx = shared.load();
+          // Every version holds 0..len contiguously
+          // 每个版本都连续包含 0..len
+          let last = idx.len() as u64 - 1;
+          assert_eq!(idx.get(last), Some(last as usize));
+          reads += 1;
+        }
+        reads
+      })
+    })
+    .collect();
+
+  for n in 1..200u64 {
+    let v: Vec<u64> = (0..1000 + n * 10).collect();
+    shared.publish(PgmData::new(&v, 32));
+  }
+  stop.store(true, Ordering::Relaxed);
+
+  let total: usize = readers.into_iter().map(|h| h.join().unwrap()).sum();
+  trace!("concurrent reads: {total}");
+  OK
+}
+
+#[test]
+fn test_spawn_rebuild() -> Void {
+  let v: Vec<u64> = (0..1000).map(|i| i * 10).collect();
+  let shared = Arc::new(SharedPgm::new(PgmData::new(&v, 16)));
+
+  let h1 = shared.spawn_rebuild(vec![15, 5, 25]);
+  let h2 = shared.spawn_rebuild(vec![99_999]);
+  h1.join().unwrap();
+  h2.join().unwrap();
+
+  let idx = shared.load();
+  assert_eq!(idx.len(), 1004);
+  assert!(idx.sorted().is_sorted());
+  for k in [5u64, 15, 25, 99_999, 0, 9990] {
+    assert!(idx.get(k).is_some(), "key={k}");
+  }
+  assert_eq!(idx.epsilon, 16);
+  OK
+}
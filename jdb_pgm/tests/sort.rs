@@ -0,0 +1,70 @@
+//! Tests for learned_sort
+//! learned_sort 测试
+
+use aok::{OK, Void};
+use jdb_pgm::sort::learned_sort;
+use log::trace;
+use rand::{Rng, SeedableRng, rngs::StdRng, seq::SliceRandom};
+
+#[static_init::constructor(0)]
+extern "C" fn _log_init() {
+  log_init::init();
+}
+
+fn check<K: jdb_pgm::Key>(mut data: Vec<K>, name: &str) {
+  let mut expect = data.clone();
+  expect.sort_unstable();
+  learned_sort(&mut data);
+  assert_eq!(data, expect, "{name}");
+}
+
+#[test]
+fn test_learned_sort() -> Void {
+  let mut rng = StdRng::seed_from_u64(3);
+  for &n in &[0usize, 1, 100, 5000, 100_000, 300_000] {
+    let uniform: Vec<u64> = (0..n).map(|_| rng.random()).collect();
+    check(uniform, "uniform");
+
+    let mut cur = 0u64;
+    let mut gaps: Vec<u64> = (0..n)
+      .map(|_| {
+        cur += rng.random_range(1..100);
+        cur
+      })
+      .collect();
+    gaps.shuffle(&mut rng);
+    check(gaps, "shuffled gaps");
+
+    let exp: Vec<u64> = (0..n).map(|_| 1u64 << rng.random_range(0..63)).collect();
+    check(exp, "exponential");
+
+    let signed: Vec<i64> = (0..n)
+      .map(|_| rng.random_range(-1_000_000..1_000_000))
+      .collect();
+    check(signed, "signed");
+  }
+  trace!("learned sort passed");
+  OK
+}
+
+#[test]
+fn test_learned_sort_adversarial() -> Void {
+  let mut rng = StdRng::seed_from_u64(5);
+  let n = 200_000;
+
+  check(vec![7u64; n], "all equal");
+  check((0..n as u64).collect(), "sorted");
+  check((0..n as u64).rev().collect(), "reversed");
+
+  let two: Vec<u32> = (0..n).map(|_| rng.random_range(0..2)).collect();
+  check(two, "two values");
+
+  // Dense cluster plus a few far outliers
+  // 密集簇加少量远离的离群值
+  let mut skew: Vec<u64> = (0..n).map(|_| rng.random_range(0..1000)).collect();
+  for v in skew.iter_mut().step_by(1000) {
+    *v = u64::MAX - rng.random_range(0..1000);
+  }
+  check(skew, "skewed");
+  OK
+}
@@ -0,0 +1,226 @@
+//! Tests for the Z-order point index
+//! Z 序点索引测试
+
+#![cfg(feature = "data")]
+
+use aok::{OK, Void};
+use jdb_pgm::{
+  ZIndex, ZPoint,
+  zorder::{bigmin, litmax},
+};
+use log::trace;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+#[static_init::constructor(0)]
+extern "C" fn _log_init() {
+  log_init::init();
+}
+
+#[test]
+fn test_morton_roundtrip() -> Void {
+  let mut rng = StdRng::seed_from_u64(5);
+  assert_eq!([1u32, 0].to_z(), 1);
+  assert_eq!([0u32, 1].to_z(), 2);
+  assert_eq!([3u32, 3].to_z(), 15);
+  assert_eq!([0u16, 0, 1].to_z(), 4);
+  assert_eq!([u32::MAX; 2].to_z(), u64::MAX);
+  assert_eq!([u64::MAX; 2].to_z(), u128::MAX);
+  assert_eq!([u32::MAX; 3].to_z(), (1u128 << 96) - 1);
+  assert_eq!([u16::MAX; 3].to_z(), (1u64 << 48) - 1);
+  for _ in 0..10_000 {
+    let p2: [u32; 2] = rng.random();
+    assert_eq!(<[u32; 2]>::from_z(p2.to_z()), p2);
+    let p2w: [u64; 2] = rng.random();
+    assert_eq!(<[u64; 2]>::from_z(p2w.to_z()), p2w);
+    let p3: [u16; 3] = rng.random();
+    assert_eq!(<[u16; 3]>::from_z(p3.to_z()), p3);
+    let p3w: [u32; 3] = rng.random();
+    assert_eq!(<[u32; 3]>::from_z(p3w.to_z()), p3w);
+    // Z order agrees with per-axis dominance
+    // Z 序与逐轴支配关系一致
+    let q2 = [p2[0].saturating_add(1), p2[1]];
+    assert!(q2.to_z() >= p2.to_z());
+  }
+  OK
+}
+
+fn in_box<const D: usize, T: Ord + Copy>(p: [T; D], lo: [T; D], hi: [T; D]) -> bool {
+  (0..D).all(|d| lo[d] <= p[d] && p[d] <= hi[d])
+}
+
+#[test]
+fn test_bigmin_litmax() -> Void {
+  let mut rng = StdRng::seed_from_u64(9);
+  for _ in 0..300 {
+    let (a, b): ([u32; 2], [u32; 2]) = (
+      [rng.random_range(0..32), rng.random_range(0..32)],
+      [rng.random_range(0..32), rng.random_range(0..32)],
+    );
+    let lo = [a[0].min(b[0]), a[1].min(b[1])];
+    let hi = [a[0].max(b[0]), a[1].max(b[1])];
+    let (zmin, zmax) = (lo.to_z(), hi.to_z());
+    for z in zmin + 1..=zmax {
+      let inside = |v: u64| in_box(<[u32; 2]>::from_z(v), lo, hi);
+      let expect_big = (z..=zmax).find(|&v| inside(v)).unwrap();
+      let expect_lit = (zmin..z).rev().find(|&v| inside(v)).unwrap();
+      assert_eq!(
+        bigmin::<[u32; 2]>(z, zmin, zmax),
+        expect_big,
+        "{lo:?} {hi:?} {z}"
+      );
+      assert_eq!(
+        litmax::<[u32; 2]>(z, zmin, zmax),
+        expect_lit,
+        "{lo:?} {hi:?} {z}"
+      );
+    }
+  }
+
+  for _ in 0..100 {
+    let a: [u16; 3] = [0, 1, 2].map(|_| rng.random_range(0..8));
+    let b: [u16; 3] = [0, 1, 2].map(|_| rng.random_range(0..8));
+    let lo = [0, 1, 2].map(|d| a[d].min(b[d]));
+    let hi = [0, 1, 2].map(|d| a[d].max(b[d]));
+    let (zmin, zmax) = (lo.to_z(), hi.to_z());
+    for z in zmin + 1..=zmax {
+      let inside = |v: u64| in_box(<[u16; 3]>::from_z(v), lo, hi);
+      let expect_big = (z..=zmax).find(|&v| inside(v)).unwrap();
+      let expect_lit = (zmin..z).rev().find(|&v| inside(v)).unwrap();
+      assert_eq!(bigmin::<[u16; 3]>(z, zmin, zmax), expect_big);
+      assert_eq!(litmax::<[u16; 3]>(z, zmin, zmax), expect_lit);
+    }
+  }
+  OK
+}
+
+/// Compare `query` with a brute-force filter
+/// 将 `query` 与暴力过滤结果比较
+fn check<const D: usize, T>(points: &[[T; D]], boxes: &[([T; D], [T; D])])
+where
+  T: Ord + Copy + core::fmt::Debug,
+  [T; D]: ZPoint,
+{
+  let index = ZIndex::new(points, 16);
+  assert_eq!(index.len(), points.len());
+  for &(a, b) in boxes {
+    let lo: [T; D] = core::array::from_fn(|d| a[d].min(b[d]));
+    let hi: [T; D] = core::array::from_fn(|d| a[d].max(b[d]));
+    let mut expect: Vec<[T; D]> = points
+      .iter()
+      .copied()
+      .filter(|&p| in_box(p, lo, hi))
+      .collect();
+    expect.sort_unstable_by_key(|p| p.to_z());
+    // Corners may be given in any order
+    // 角点可以任意顺序给出
+    assert_eq!(index.query(a, b), expect, "{a:?} {b:?}");
+    assert_eq!(index.query(b, a), expect, "{b:?} {a:?}");
+  }
+}
+
+#[test]
+fn test_query_2d() -> Void {
+  let mut rng = StdRng::seed_from_u64(13);
+  // Dense grid with duplicates plus clustered tiles
+  // 含重复的稠密网格加聚簇瓦片
+  let mut points: Vec<[u32; 2]> = (0..20_000)
+    .map(|_| [rng.random_range(0..1000), rng.random_range(0..1000)])
+    .collect();
+  points.extend((0..5_000).map(|_| {
+    [
+      rng.random_range(500_000..500_100),
+      rng.random_range(7_000..7_050),
+    ]
+  }));
+  let mut boxes: Vec<([u32; 2], [u32; 2])> = (0..200)
+    .map(|_| {
+      (
+        [rng.random_range(0..1100), rng.random_range(0..1100)],
+        [rng.random_range(0..1100), rng.random_range(0..1100)],
+      )
+    })
+    .collect();
+  boxes.push(([0, 0], [u32::MAX, u32::MAX]));
+  boxes.push(([500_020, 7_010], [500_060, 7_030]));
+  boxes.push(([5, 5], [5, 5]));
+  boxes.push(([2000, 2000], [3000, 3000]));
+  check(&points, &boxes);
+  trace!("2d query passed");
+  OK
+}
+
+#[test]
+fn test_query_3d() -> Void {
+  let mut rng = StdRng::seed_from_u64(17);
+  let points: Vec<[u32; 3]> = (0..20_000)
+    .map(|_| {
+      [
+        rng.random_range(0..200),
+        rng.random_range(0..200),
+        rng.random_range(1_000_000..1_000_200),
+      ]
+    })
+    .collect();
+  let boxes: Vec<_> = (0..100)
+    .map(|_| {
+      (
+        [
+          rng.random_range(0..220),
+          rng.random_range(0..220),
+          rng.random_range(999_990..1_000_220),
+        ],
+        [
+          rng.random_range(0..220),
+          rng.random_range(0..220),
+          rng.random_range(999_990..1_000_220),
+        ],
+      )
+    })
+    .collect();
+  check(&points, &boxes);
+
+  let small: Vec<[u16; 3]> = (0..5_000).map(|_| rng.random()).collect();
+  let boxes: Vec<([u16; 3], [u16; 3])> = (0..50).map(|_| (rng.random(), rng.random())).collect();
+  check(&small, &boxes);
+  OK
+}
+
+#[test]
+fn test_query_wide_keys() -> Void {
+  let mut rng = StdRng::seed_from_u64(19);
+  // Coordinates near u64::MAX exercise the top bits of u128 keys
+  // 接近 u64::MAX 的坐标覆盖 u128 键的最高位
+  let base = u64::MAX - 10_000;
+  let mut points: Vec<[u64; 2]> = (0..10_000)
+    .map(|_| {
+      [
+        rng.random_range(base..=u64::MAX),
+        rng.random_range(0..10_000),
+      ]
+    })
+    .collect();
+  // The widest code, reached only by a range ending at u128::MAX
+  // 最大编码，仅由终点为 u128::MAX 的区间覆盖
+  points.push([u64::MAX, u64::MAX]);
+  let mut boxes: Vec<_> = (0..100)
+    .map(|_| {
+      (
+        [
+          rng.random_range(base..=u64::MAX),
+          rng.random_range(0..11_000),
+        ],
+        [
+          rng.random_range(base..=u64::MAX),
+          rng.random_range(0..11_000),
+        ],
+      )
+    })
+    .collect();
+  boxes.push(([base, 0], [u64::MAX, u64::MAX]));
+  check(&points, &boxes);
+
+  let empty = ZIndex::<[u32; 2]>::new(&[], 16);
+  assert!(empty.is_empty());
+  assert!(empty.query([0, 0], [10, 10]).is_empty());
+  OK
+}
@@ -1,6 +1,8 @@
 //! Compact Bit Reader/Writer for PGM residuals
 //! 用于 PGM 残差的紧凑位读写器
 
+use alloc::vec::Vec;
+
 #[inline(always)]
 pub fn read_bits(data: &[u64], start_bit: usize, bits: u8) -> u64 {
   let word_idx = start_bit / 64;
@@ -22,6 +24,121 @@ pub fn read_bits(data: &[u64], start_bit: usize, bits: u8) -> u64 {
   }
 }
 
+/// Unpack `out.len()` consecutive `width`-bit values (0..=64) starting at
+/// `start_bit`. Uses AVX2 gathers when the CPU has them (x86_64 with the
+/// `std` feature, widths up to 56), otherwise `unpack_scalar`.
+/// 从 `start_bit` 起解包 `out.len()` 个连续的 `width` 位值（0..=64）。
+/// CPU 支持时使用 AVX2 gather（x86_64 且启用 `std` 特性，位宽不超过 56），
+/// 否则使用 `unpack_scalar`。
+///
+/// # Panics
+/// If `width > 64` or the run does not fit in `data`.
+pub fn unpack(data: &[u64], start_bit: usize, width: u8, out: &mut [u64]) {
+  check_run(data, start_bit, width, out.len());
+  #[allow(unused_mut)]
+  let mut done = 0;
+  #[cfg(all(feature = "std", target_arch = "x86_64"))]
+  if (1..=56).contains(&width) && std::is_x86_feature_detected!("avx2") {
+    // SAFETY: AVX2 is present; the run was checked against `data`
+    done = unsafe { avx2::unpack(data, start_bit, width, out) };
+  }
+  unpack_tail(
+    data,
+    start_bit + done * width as usize,
+    width,
+    &mut out[done..],
+  );
+}
+
+/// Scalar `unpack`, one value per step.
+/// 标量版 `unpack`，每步一个值。
+///
+/// # Panics
+/// If `width > 64` or the run does not fit in `data`.
+pub fn unpack_scalar(data: &[u64], start_bit: usize, width: u8, out: &mut [u64]) {
+  check_run(data, start_bit, width, out.len());
+  unpack_tail(data, start_bit, width, out);
+}
+
+#[inline]
+fn check_run(data: &[u64], start_bit: usize, width: u8, len: usize) {
+  assert!(width <= 64, "bit width {width} > 64");
+  let end = (width as usize)
+    .checked_mul(len)
+    .and_then(|n| n.checked_add(start_bit));
+  assert!(
+    width == 0 || end.is_some_and(|end| end <= data.len() * 64),
+    "bit run out of range"
+  );
+}
+
+/// Caller has checked the run.
+/// 调用方已检查该区间。
+#[inline]
+fn unpack_tail(data: &[u64], start_bit: usize, width: u8, out: &mut [u64]) {
+  if width == 0 {
+    out.fill(0);
+    return;
+  }
+  let mut pos = start_bit;
+  for v in out {
+    *v = read_bits(data, pos, width);
+    pos += width as usize;
+  }
+}
+
+#[cfg(all(feature = "std", target_arch = "x86_64"))]
+mod avx2 {
+  use core::arch::x86_64::*;
+
+  /// Four lanes per step: gather the 8 bytes holding each value, shift by
+  /// the bit remainder, mask. Stops before a gather would pass the end of
+  /// `data`; returns how many values were written.
+  /// 每步四路：gather 各值所在的 8 字节，按位余数右移并掩码。在 gather
+  /// 将越过 `data` 末尾之前停止；返回已写出的值个数。
+  #[target_feature(enable = "avx2")]
+  pub(super) unsafe fn unpack(data: &[u64], start_bit: usize, width: u8, out: &mut [u64]) -> usize {
+    let bytes = data.len() * 8;
+    let w = width as usize;
+    let base = data.as_ptr().cast::<i64>();
+    let mask = _mm256_set1_epi64x(((1u64 << w) - 1) as i64);
+    let seven = _mm256_set1_epi64x(7);
+    let step = _mm256_set1_epi64x(4 * w as i64);
+    let s = start_bit as i64;
+    let wi = w as i64;
+    let mut pos = _mm256_setr_epi64x(s, s + wi, s + 2 * wi, s + 3 * wi);
+
+    let mut i = 0;
+    while i + 4 <= out.len() && ((start_bit + (i + 3) * w) >> 3) + 8 <= bytes {
+      // SAFETY: every lane reads 8 bytes ending within `data`
+      unsafe {
+        let words = _mm256_i64gather_epi64::<1>(base, _mm256_srli_epi64::<3>(pos));
+        let vals = _mm256_and_si256(_mm256_srlv_epi64(words, _mm256_and_si256(pos, seven)), mask);
+        _mm256_storeu_si256(out.as_mut_ptr().add(i).cast::<__m256i>(), vals);
+      }
+      pos = _mm256_add_epi64(pos, step);
+      i += 4;
+    }
+    i
+  }
+}
+
+/// Overwrite `bits` (1..=64) bits at `start_bit` in place.
+/// 原地覆写 `start_bit` 处的 `bits`（1..=64）位。
+#[inline]
+pub fn write_bits(data: &mut [u64], start_bit: usize, bits: u8, val: u64) {
+  let word_idx = start_bit / 64;
+  let bit_idx = (start_bit % 64) as u32;
+  let mask = !0u64 >> (64 - bits);
+  let val = val & mask;
+
+  data[word_idx] = (data[word_idx] & !(mask << bit_idx)) | (val << bit_idx);
+  if bit_idx + bits as u32 > 64 {
+    let shift = 64 - bit_idx;
+    data[word_idx + 1] = (data[word_idx + 1] & !(mask >> shift)) | (val >> shift);
+  }
+}
+
 pub struct BitWriter {
   pub data: Vec<u64>,
   current: u64,
@@ -39,6 +156,27 @@ impl BitWriter {
     }
   }
 
+  /// Continue writing after the first `bits` bits of `data` (as returned by
+  /// `finish`); anything past them is dropped.
+  /// 在 `data`（`finish` 的返回值）的前 `bits` 位之后继续写入；其后内容丢弃。
+  pub fn resume(mut data: Vec<u64>, bits: usize) -> Self {
+    let bits_in_current = (bits % 64) as u8;
+    let current = if bits_in_current > 0 {
+      data
+        .get(bits / 64)
+        .map_or(0, |w| w & (!0u64 >> (64 - bits_in_current)))
+    } else {
+      0
+    };
+    data.truncate(bits / 64);
+    Self {
+      data,
+      current,
+      bits_in_current,
+      total_bits: bits,
+    }
+  }
+
   pub fn write(&mut self, val: u64, bits: u8) {
     if bits == 0 {
       return;
@@ -63,6 +201,22 @@ impl BitWriter {
     self.total_bits += bits as usize;
   }
 
+  /// Append the `bits` bits of `data` that start at `start_bit`.
+  /// 追加 `data` 中自 `start_bit` 起的 `bits` 位。
+  ///
+  /// # Panics
+  /// If the run does not fit in `data`.
+  pub fn write_run(&mut self, data: &[u64], start_bit: usize, bits: usize) {
+    let end = start_bit + bits;
+    assert!(end <= data.len() * 64, "bit run out of range");
+    let mut at = start_bit;
+    while at < end {
+      let n = (end - at).min(64) as u8;
+      self.write(read_bits(data, at, n), n);
+      at += n as usize;
+    }
+  }
+
   pub fn current_bit_offset(&self) -> usize {
     self.total_bits
   }
@@ -1,7 +1,10 @@
+use alloc::string::String;
+
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum PgmError {
+  #[cfg(feature = "std")]
   #[error("IO error: {0}")]
   Io(#[from] std::io::Error),
   #[error("Serialization error: {0}")]
@@ -10,4 +13,4 @@ pub enum PgmError {
   InvalidData(String),
 }
 
-pub type Result<T> = std::result::Result<T, PgmError>;
+pub type Result<T> = core::result::Result<T, PgmError>;
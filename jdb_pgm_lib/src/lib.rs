@@ -1,2 +1,9 @@
+//! Core library for jdb_pgm (no_std + alloc, `std` feature for I/O errors)
+//! jdb_pgm 核心库（no_std + alloc，`std` 特性启用 I/O 错误）
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
 pub mod bits;
 pub mod error;